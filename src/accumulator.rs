@@ -0,0 +1,223 @@
+use std::path::Path;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+// Cuántas muestras recibe cada píxel. Un píxel deja de muestrearse cuando el error
+// estimado de su promedio baja del umbral, siempre entre `min_samples` y `max_samples`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplingBudget {
+    pub min_samples: u32,
+    pub max_samples: u32,
+    pub threshold: f32, // Error relativo aceptado; 0 = todos los píxeles llegan a `max_samples`
+}
+
+impl SamplingBudget {
+    // Las mismas muestras para todos los píxeles
+    pub fn uniform(samples: u32) -> Self {
+        SamplingBudget {
+            min_samples: samples,
+            max_samples: samples,
+            threshold: 0.0,
+        }
+    }
+
+    pub fn adaptive(min_samples: u32, max_samples: u32, threshold: f32) -> Self {
+        SamplingBudget {
+            min_samples: min_samples.min(max_samples),
+            max_samples,
+            threshold,
+        }
+    }
+}
+
+// Brillo percibido de un color lineal
+fn luminance(color: &Vec3) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// Suma de las muestras de cada píxel a lo largo de varios cuadros, para que la imagen
+// del trazador de caminos converja mientras la cámara y la escena no cambien. Guarda
+// también la varianza del brillo para repartir las muestras donde hay más ruido.
+pub struct Accumulator {
+    pub width: usize,
+    pub height: usize,
+    pub budget: SamplingBudget,
+    sum: Vec<Vec3>,
+    sum_squares: Vec<f32>, // Suma de los cuadrados del brillo de cada muestra
+    counts: Vec<u32>,      // Muestras sumadas en cada píxel
+    passes: u32,           // Cuadros acumulados
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize, budget: SamplingBudget) -> Self {
+        Accumulator {
+            width,
+            height,
+            budget,
+            sum: vec![Vec3::zeros(); width * height],
+            sum_squares: vec![0.0; width * height],
+            counts: vec![0; width * height],
+            passes: 0,
+        }
+    }
+
+    // Descarta lo acumulado (la cámara se movió o la escena cambió)
+    pub fn reset(&mut self) {
+        self.sum.fill(Vec3::zeros());
+        self.sum_squares.fill(0.0);
+        self.counts.fill(0);
+        self.passes = 0;
+    }
+
+    // Cuadros acumulados desde el último reinicio
+    pub fn samples(&self) -> u32 {
+        self.passes
+    }
+
+    // Muestras por píxel en promedio
+    pub fn mean_samples(&self) -> f32 {
+        self.counts.iter().map(|&count| count as f64).sum::<f64>() as f32 / self.counts.len().max(1) as f32
+    }
+
+    // Error relativo estimado del promedio del píxel (desviación estándar de la media
+    // dividida por el brillo, con un mínimo para no exigir demasiado a los píxeles oscuros)
+    pub fn relative_error(&self, index: usize) -> f32 {
+        let count = self.counts[index];
+        if count < 2 {
+            return f32::INFINITY;
+        }
+        let n = count as f32;
+        let mean = luminance(&self.sum[index]) / n;
+        let variance = ((self.sum_squares[index] / n - mean * mean) * n / (n - 1.0)).max(0.0);
+        (variance / n).sqrt() / (mean + 0.05)
+    }
+
+    // Indica si el píxel todavía necesita muestras
+    pub fn needs_samples(&self, index: usize) -> bool {
+        let count = self.counts[index];
+        count < self.budget.min_samples
+            || (count < self.budget.max_samples && self.relative_error(index) > self.budget.threshold)
+    }
+
+    // Ningún píxel necesita más muestras
+    pub fn is_converged(&self) -> bool {
+        (0..self.counts.len()).all(|index| !self.needs_samples(index))
+    }
+
+    // Suma un cuadro con una muestra para algunos píxeles: (índice en orden de filas, color)
+    pub fn add_samples(&mut self, samples: &[(usize, Vec3)]) {
+        for (index, sample) in samples {
+            let brightness = luminance(sample);
+            self.sum[*index] += sample;
+            self.sum_squares[*index] += brightness * brightness;
+            self.counts[*index] += 1;
+        }
+        self.passes += 1;
+    }
+
+    // Promedio de las muestras del píxel
+    pub fn average(&self, x: usize, y: usize) -> Vec3 {
+        let index = y * self.width + x;
+        self.sum[index] / self.counts[index].max(1) as f32
+    }
+
+    // Promedio que tendría el píxel con una muestra más, para mostrar un cuadro a medias
+    pub fn preview(&self, index: usize, sample: &Vec3) -> Vec3 {
+        (self.sum[index] + sample) / (self.counts[index] + 1) as f32
+    }
+
+    // Promedio de todos los píxeles, en orden de filas
+    pub fn image(&self) -> Vec<Vec3> {
+        self.sum.iter().zip(&self.counts).map(|(sum, &count)| sum / count.max(1) as f32).collect()
+    }
+
+    // Copia el promedio al framebuffer
+    pub fn resolve(&self, framebuffer: &mut Framebuffer) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                framebuffer.set_current_color(Color::from_vec3(self.average(x, y)));
+                framebuffer.point(x as f32, y as f32);
+            }
+        }
+    }
+
+    // Mapa de calor de las muestras por píxel en RGB de 8 bits: azul con pocas muestras,
+    // verde a mitad del máximo y rojo con `max_samples`
+    pub fn heatmap(&self) -> Vec<u8> {
+        let max_samples = self.budget.max_samples.max(1) as f32;
+        self.counts
+            .iter()
+            .flat_map(|&count| {
+                let t = (count as f32 / max_samples).clamp(0.0, 1.0);
+                let color = if t < 0.5 {
+                    Vec3::new(0.0, t * 2.0, 1.0 - t * 2.0)
+                } else {
+                    Vec3::new(t * 2.0 - 1.0, 2.0 - t * 2.0, 0.0)
+                };
+                let color = Color::from_vec3(color);
+                [color.red, color.green, color.blue]
+            })
+            .collect()
+    }
+
+    // Guarda el mapa de calor como PNG
+    pub fn save_heatmap(&self, path: &Path) -> image::ImageResult<()> {
+        image::save_buffer(path, &self.heatmap(), self.width as u32, self.height as u32, image::ExtendedColorType::Rgb8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_frame(values: &[Vec3]) -> Vec<(usize, Vec3)> {
+        values.iter().copied().enumerate().collect()
+    }
+
+    #[test]
+    fn test_average_and_reset() {
+        let mut accumulator = Accumulator::new(2, 1, SamplingBudget::uniform(16));
+        accumulator.add_samples(&full_frame(&[Vec3::new(1.0, 0.0, 0.0), Vec3::zeros()]));
+        accumulator.add_samples(&full_frame(&[Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.5, 0.0)]));
+        assert_eq!(accumulator.samples(), 2);
+        assert_eq!(accumulator.average(0, 0), Vec3::new(0.5, 0.0, 0.0));
+        assert_eq!(accumulator.average(1, 0), Vec3::new(0.0, 0.25, 0.0));
+
+        accumulator.reset();
+        assert_eq!(accumulator.samples(), 0);
+        assert_eq!(accumulator.average(0, 0), Vec3::zeros());
+    }
+
+    #[test]
+    fn test_uniform_budget_samples_every_pixel() {
+        let mut accumulator = Accumulator::new(2, 1, SamplingBudget::uniform(3));
+        for _ in 0..3 {
+            assert!(accumulator.needs_samples(0) && accumulator.needs_samples(1));
+            accumulator.add_samples(&full_frame(&[Vec3::repeat(0.5), Vec3::repeat(0.5)]));
+        }
+        assert!(accumulator.is_converged());
+    }
+
+    #[test]
+    fn test_noisy_pixels_keep_sampling() {
+        // El píxel 0 es constante y el 1 alterna entre negro y blanco
+        let mut accumulator = Accumulator::new(2, 1, SamplingBudget::adaptive(4, 64, 0.05));
+        for pass in 0..64 {
+            let noisy = if pass % 2 == 0 { Vec3::zeros() } else { Vec3::repeat(1.0) };
+            let samples: Vec<(usize, Vec3)> = (0..2)
+                .filter(|&index| accumulator.needs_samples(index))
+                .map(|index| (index, if index == 0 { Vec3::repeat(0.5) } else { noisy }))
+                .collect();
+            accumulator.add_samples(&samples);
+        }
+
+        assert_eq!(accumulator.counts[0], 4);
+        assert_eq!(accumulator.counts[1], 64);
+        assert!(accumulator.is_converged());
+
+        let heatmap = accumulator.heatmap();
+        assert!(heatmap[2] > heatmap[0]); // Poco muestreado: azul
+        assert!(heatmap[3] > heatmap[5]); // Al máximo: rojo
+    }
+}
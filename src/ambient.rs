@@ -0,0 +1,127 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::ray_intersect::RayCone;
+use crate::sampling::{cosine_hemisphere, hammersley, hash_offset};
+use crate::tracing::{RenderContext, scene_intersect, BACKGROUND_COLOR};
+
+// Luz de ambiente que llega del cielo desde todo el hemisferio. Cuánta llega a un punto se
+// estima con rayos de oclusión ambiental: los que chocan con algo cerca no ven el cielo.
+#[derive(Clone, Copy, Debug)]
+pub struct SkyLight {
+    pub color: Color,
+    pub intensity: f32,
+    pub samples: u32,      // Rayos de oclusión en el primer impacto
+    pub max_distance: f32, // Los obstáculos más lejanos que esto no tapan el cielo
+}
+
+impl SkyLight {
+    pub fn new(intensity: f32) -> Self {
+        SkyLight {
+            color: BACKGROUND_COLOR,
+            intensity,
+            samples: 16,
+            max_distance: 1.5,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    // Fracción del cielo (ponderada por el coseno) que se ve desde `point`, entre 0 y 1.
+    // `samples` permite usar menos rayos en los rebotes.
+    pub fn visibility(&self, point: &Vec3, normal: &Vec3, samples: u32, context: &RenderContext, cone: RayCone) -> f32 {
+        let samples = samples.max(1);
+        let origin = point + normal * 1e-3;
+        // Cada punto gira el patrón de muestras: ruido fino en lugar de manchas repetidas
+        let (offset_u, offset_v) = hash_offset(point);
+
+        let visible = (0..samples)
+            .filter(|&index| {
+                let (u1, u2) = hammersley(index, samples);
+                let direction = cosine_hemisphere(normal, (u1 + offset_u).fract(), (u2 + offset_v).fract());
+                let hit = scene_intersect(&origin, &direction, context, cone);
+                !hit.is_intersecting || hit.distance > self.max_distance
+            })
+            .count();
+
+        visible as f32 / samples as f32
+    }
+
+    // Irradiancia del cielo (lineal, 1.0 = 255) que recibe una superficie difusa
+    pub fn irradiance(&self, point: &Vec3, normal: &Vec3, samples: u32, context: &RenderContext, cone: RayCone) -> Vec3 {
+        self.color.to_vec3() * (self.intensity * self.visibility(point, normal, samples, context, cone))
+    }
+}
+
+// Pase de depuración: la visibilidad del cielo en cada punto en escala de grises
+// (blanco = cielo abierto, negro = rincón tapado)
+pub fn ambient_occlusion_pass(ray_origin: &Vec3, ray_direction: &Vec3, sky_light: &SkyLight, context: &RenderContext, cone: RayCone) -> Color {
+    let intersect = scene_intersect(ray_origin, ray_direction, context, cone);
+    if !intersect.is_intersecting {
+        return Color::new(255, 255, 255);
+    }
+
+    let facing = if ray_direction.dot(&intersect.normal) < 0.0 { 1.0 } else { -1.0 };
+    let visibility = sky_light.visibility(&intersect.point, &(intersect.normal * facing), sky_light.samples, context, cone);
+    Color::from_vec3(Vec3::repeat(visibility))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::light::Light;
+    use crate::materials::TextureManager;
+    use crate::material_library::MaterialLibrary;
+    use crate::ray_intersect::RayIntersect;
+
+    fn visibility_with(objects: &[Box<dyn RayIntersect>], sky_light: &SkyLight) -> f32 {
+        let texture_manager = TextureManager::new();
+        let mut materials = MaterialLibrary::new();
+        materials.add("block", MaterialLibrary::default_material());
+        let lights: [Light; 0] = [];
+        let context = RenderContext {
+            objects,
+            lights: &lights,
+            texture_manager: &texture_manager,
+            materials: &materials,
+            time: 0.0,
+            fog: None,
+            clouds: None,
+            sky_light: None,
+        };
+        let cone = RayCone::from_pixel(1.0, 600.0);
+        sky_light.visibility(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 1.0, 0.0), sky_light.samples, &context, cone)
+    }
+
+    #[test]
+    fn test_open_sky_is_fully_visible() {
+        assert_eq!(visibility_with(&[], &SkyLight::new(1.0)), 1.0);
+    }
+
+    #[test]
+    fn test_roof_blocks_sky_within_max_distance() {
+        let roof: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(Cube::new(Vec3::new(-50.0, 1.0, -50.0), Vec3::new(50.0, 1.5, 50.0), 0)),
+        ];
+        assert!(visibility_with(&roof, &SkyLight::new(1.0).with_max_distance(5.0)) < 0.1);
+
+        // Un techo más lejano que `max_distance` no cuenta como oclusión
+        let far_roof: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(Cube::new(Vec3::new(-50.0, 8.0, -50.0), Vec3::new(50.0, 9.0, 50.0), 0)),
+        ];
+        assert!(visibility_with(&far_roof, &SkyLight::new(1.0).with_max_distance(5.0)) > 0.5);
+    }
+}
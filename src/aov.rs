@@ -0,0 +1,342 @@
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use crate::color::Color;
+use crate::ray_intersect::RayCone;
+use crate::tracing::{RenderContext, flush_all_ray_counts, scene_intersect};
+use crate::whitted::{cast_ray, shade, apply_fog};
+
+// Pases de render (AOV): además de la imagen final, datos de cada píxel para composición
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Beauty,     // Imagen final del trazador de Whitted (o del de caminos en modo sin ventana)
+    Depth,      // Distancia de la cámara al impacto; infinito si el rayo no golpea nada
+    Position,   // Punto golpeado en coordenadas del mundo
+    Normal,     // Normal de sombreado orientada hacia la cámara
+    Uv,         // Coordenadas de textura (u, v, 0)
+    Albedo,     // Color difuso del material, sin luz
+    ObjectId,   // Índice del objeto más uno (0 = cielo)
+    MaterialId, // Índice del material más uno (0 = cielo)
+    Diffuse,    // Componentes de la imagen final en el primer impacto, sin niebla
+    Specular,
+    Reflection,
+    Refraction,
+    Emission,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 13] = [
+        Aov::Beauty, Aov::Depth, Aov::Position, Aov::Normal, Aov::Uv, Aov::Albedo,
+        Aov::ObjectId, Aov::MaterialId, Aov::Diffuse, Aov::Specular, Aov::Reflection,
+        Aov::Refraction, Aov::Emission,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Beauty => "beauty",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::Normal => "normal",
+            Aov::Uv => "uv",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Diffuse => "diffuse",
+            Aov::Specular => "specular",
+            Aov::Reflection => "reflection",
+            Aov::Refraction => "refraction",
+            Aov::Emission => "emission",
+        }
+    }
+
+    // Canales de la capa EXR; cada uno guarda una componente del Vec3 del píxel
+    fn channels(self) -> &'static [&'static str] {
+        match self {
+            Aov::Depth => &["Z"],
+            Aov::ObjectId | Aov::MaterialId => &["id"],
+            Aov::Uv => &["U", "V"],
+            _ => &["R", "G", "B"],
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// Valores de todos los pases en un píxel, en el orden de `Aov::ALL`
+pub type AovSample = [Vec3; 13];
+
+// Albedo de los píxeles de cielo, en el pase de albedo y en las guías del filtro de ruido:
+// neutro, para que el filtro no cambie su color al separar la luz de la textura
+pub const SKY_ALBEDO: f32 = 1.0;
+
+// Pases de un rayo primario. Los componentes de la imagen se toman del primer impacto
+// y no incluyen la niebla entre la cámara y la superficie; la imagen final sí.
+pub fn sample_aovs(ray_origin: &Vec3, ray_direction: &Vec3, context: &RenderContext, cone: RayCone) -> AovSample {
+    let mut sample = [Vec3::zeros(); 13];
+    let intersect = scene_intersect(ray_origin, ray_direction, context, cone);
+
+    if !intersect.is_intersecting {
+        let sky = cast_ray(ray_origin, ray_direction, context, 0, cone, None);
+        sample[Aov::Beauty.index()] = sky;
+        sample[Aov::Depth.index()] = Vec3::repeat(f32::INFINITY);
+        sample[Aov::Albedo.index()] = Vec3::repeat(SKY_ALBEDO);
+        return sample;
+    }
+
+    let material = context.materials.get(intersect.material_id);
+    let footprint = cone.uv_footprint(&intersect, ray_direction);
+    let facing = if ray_direction.dot(&intersect.normal) < 0.0 { 1.0 } else { -1.0 };
+    let normal = material.shading_normal(&intersect, footprint, context.time, context.texture_manager) * facing;
+
+    let components = shade(ray_origin, ray_direction, &intersect, context, 0, cone, None);
+    let beauty = apply_fog(components.total(), ray_origin, ray_direction, intersect.distance, context, cone);
+
+    sample[Aov::Beauty.index()] = beauty;
+    sample[Aov::Depth.index()] = Vec3::repeat(intersect.distance);
+    sample[Aov::Position.index()] = intersect.point;
+    sample[Aov::Normal.index()] = normal;
+    sample[Aov::Uv.index()] = Vec3::new(intersect.u, intersect.v, 0.0);
+    sample[Aov::Albedo.index()] = material.get_diffuse_color(&intersect, footprint, context.time, context.texture_manager).to_vec3();
+    sample[Aov::ObjectId.index()] = Vec3::repeat((intersect.object_id + 1) as f32);
+    sample[Aov::MaterialId.index()] = Vec3::repeat((intersect.material_id + 1) as f32);
+    sample[Aov::Diffuse.index()] = components.diffuse;
+    sample[Aov::Specular.index()] = components.specular;
+    sample[Aov::Reflection.index()] = components.reflection;
+    sample[Aov::Refraction.index()] = components.refraction;
+    sample[Aov::Emission.index()] = components.emission;
+    sample
+}
+
+// Todos los pases de una imagen, guardados como valores lineales por píxel en orden de filas
+pub struct RenderPasses {
+    pub width: usize,
+    pub height: usize,
+    layers: Vec<Vec<Vec3>>, // Una capa por pase, en el orden de `Aov::ALL`
+}
+
+impl RenderPasses {
+    pub fn new(width: usize, height: usize) -> Self {
+        RenderPasses {
+            width,
+            height,
+            layers: vec![vec![Vec3::zeros(); width * height]; Aov::ALL.len()],
+        }
+    }
+
+    // Renderiza los pases lanzando un rayo por píxel desde `eye`; `direction(x, y)` da
+    // la dirección del rayo que pasa por el centro del píxel
+    pub fn render<F>(width: usize, height: usize, eye: &Vec3, direction: F, context: &RenderContext, cone: RayCone) -> Self
+    where
+        F: Fn(f32, f32) -> Vec3 + Sync,
+    {
+        let samples: Vec<AovSample> = (0..width * height)
+            .into_par_iter()
+            .map(|index| {
+                let ray_direction = direction((index % width) as f32, (index / width) as f32);
+                sample_aovs(eye, &ray_direction, context, cone)
+            })
+            .collect();
+        flush_all_ray_counts();
+
+        let mut passes = RenderPasses::new(width, height);
+        for (index, sample) in samples.iter().enumerate() {
+            for (layer, value) in passes.layers.iter_mut().zip(sample) {
+                layer[index] = *value;
+            }
+        }
+        passes
+    }
+
+    pub fn layer(&self, aov: Aov) -> &[Vec3] {
+        &self.layers[aov.index()]
+    }
+
+    pub fn layer_mut(&mut self, aov: Aov) -> &mut [Vec3] {
+        &mut self.layers[aov.index()]
+    }
+
+    // Versión visible del pase en RGB de 8 bits: los colores tal cual, la profundidad
+    // clara cerca y oscura lejos, posiciones escaladas a la escena y un color por id
+    pub fn to_rgb8(&self, aov: Aov) -> Vec<u8> {
+        let layer = self.layer(aov);
+        let finite = || layer.iter().filter(|value| value.x.is_finite());
+        let (min, max) = match aov {
+            Aov::Depth | Aov::Position => finite().fold(
+                (Vec3::repeat(f32::INFINITY), Vec3::repeat(f32::NEG_INFINITY)),
+                |(min, max), value| (min.inf(value), max.sup(value)),
+            ),
+            _ => (Vec3::zeros(), Vec3::repeat(1.0)),
+        };
+        let range = (max - min).map(|extent| extent.max(1e-6));
+
+        layer
+            .iter()
+            .flat_map(|value| {
+                let color = match aov {
+                    Aov::Depth if !value.x.is_finite() => Color::black(),
+                    Aov::Depth => Color::from_vec3(Vec3::repeat(1.0 - (value.x - min.x) / range.x)),
+                    Aov::Position => Color::from_vec3((value - min).component_div(&range)),
+                    Aov::Normal if *value == Vec3::zeros() => Color::black(),
+                    Aov::Normal => Color::from_vec3(value * 0.5 + Vec3::repeat(0.5)),
+                    Aov::Uv => Color::from_vec3(Vec3::new(value.x.rem_euclid(1.0), value.y.rem_euclid(1.0), 0.0)),
+                    Aov::ObjectId | Aov::MaterialId => id_color(value.x as u32),
+                    _ => Color::from_vec3(*value),
+                };
+                [color.red, color.green, color.blue]
+            })
+            .collect()
+    }
+
+    pub fn save_png(&self, aov: Aov, path: &Path) -> image::ImageResult<()> {
+        image::save_buffer(path, &self.to_rgb8(aov), self.width as u32, self.height as u32, image::ExtendedColorType::Rgb8)
+    }
+
+    // Guarda cada pase como `<nombre>.<pase>.png` junto a `path` y devuelve las rutas escritas
+    pub fn save_pngs(&self, path: &Path) -> image::ImageResult<Vec<PathBuf>> {
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("render");
+        Aov::ALL
+            .iter()
+            .map(|&aov| {
+                let pass_path = path.with_file_name(format!("{}.{}.png", stem, aov.name()));
+                self.save_png(aov, &pass_path)?;
+                Ok(pass_path)
+            })
+            .collect()
+    }
+
+    // Guarda todos los pases en un solo EXR, una capa por pase, con los valores en coma flotante
+    pub fn save_exr(&self, path: &Path) -> exr::error::Result<()> {
+        use exr::prelude::*;
+
+        let size = (self.width, self.height);
+        let layers: Vec<_> = Aov::ALL
+            .iter()
+            .map(|&aov| {
+                let layer = self.layer(aov);
+                let channels = aov.channels()
+                    .iter()
+                    .enumerate()
+                    .map(|(component, name)| {
+                        let samples = layer.iter().map(|value| value[component]).collect();
+                        AnyChannel::new(*name, FlatSamples::F32(samples))
+                    })
+                    .collect();
+                Layer::new(size, LayerAttributes::named(aov.name()), Encoding::FAST_LOSSLESS, AnyChannels::sort(channels))
+            })
+            .collect();
+
+        let image = Image::from_layers(ImageAttributes::new(IntegerBounds::from_dimensions(size)), layers);
+        image.write().to_file(path)
+    }
+}
+
+// Color fijo y bien distinto para cada id; el 0 (cielo) es negro
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return Color::black();
+    }
+    let hash = id.wrapping_mul(0x9E37_79B9) ^ (id >> 3).wrapping_mul(0x85EB_CA6B);
+    Color::new((hash >> 24) as i32 | 0x40, ((hash >> 16) & 0xFF) as i32 | 0x40, ((hash >> 8) & 0xFF) as i32 | 0x40)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::light::Light;
+    use crate::materials::TextureManager;
+    use crate::material_library::MaterialLibrary;
+    use crate::ray_intersect::RayIntersect;
+
+    fn scene_sample(ray_direction: Vec3, light_intensity: f32) -> AovSample {
+        let texture_manager = TextureManager::new();
+        let mut materials = MaterialLibrary::new();
+        materials.add("block", MaterialLibrary::default_material());
+        materials.add("red", MaterialLibrary::default_material());
+        let objects: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(Cube::new(Vec3::new(-10.0, -10.0, -20.0), Vec3::new(10.0, 10.0, -19.0), 0)),
+            Box::new(Cube::new(Vec3::new(-1.0, -1.0, -6.0), Vec3::new(1.0, 1.0, -5.0), 1)),
+        ];
+        let lights = [Light::new(Vec3::new(0.0, 0.0, 10.0), Color::new(255, 255, 255), light_intensity)];
+        let context = RenderContext {
+            objects: &objects,
+            lights: &lights,
+            texture_manager: &texture_manager,
+            materials: &materials,
+            time: 0.0,
+            fog: None,
+            clouds: None,
+            sky_light: None,
+        };
+        sample_aovs(&Vec3::zeros(), &ray_direction.normalize(), &context, RayCone::from_pixel(1.0, 600.0))
+    }
+
+    #[test]
+    fn test_geometry_passes_come_from_the_first_hit() {
+        let sample = scene_sample(Vec3::new(0.0, 0.0, -1.0), 1.0);
+        assert!((sample[Aov::Depth.index()].x - 5.0).abs() < 1e-4);
+        assert!((sample[Aov::Position.index()] - Vec3::new(0.0, 0.0, -5.0)).norm() < 1e-4);
+        assert!((sample[Aov::Normal.index()] - Vec3::new(0.0, 0.0, 1.0)).norm() < 1e-4);
+        assert_eq!(sample[Aov::ObjectId.index()].x, 2.0);
+        assert_eq!(sample[Aov::MaterialId.index()].x, 2.0);
+        assert_eq!(sample[Aov::Albedo.index()], Vec3::repeat(1.0));
+    }
+
+    #[test]
+    fn test_components_add_up_to_beauty() {
+        let sample = scene_sample(Vec3::new(0.0, 0.0, -1.0), 1.0);
+        let components = [Aov::Diffuse, Aov::Specular, Aov::Reflection, Aov::Refraction, Aov::Emission]
+            .iter()
+            .map(|aov| sample[aov.index()])
+            .sum::<Vec3>();
+        assert!((components - sample[Aov::Beauty.index()]).norm() < 1e-4);
+        assert!(sample[Aov::Diffuse.index()].x > 0.0);
+    }
+
+    #[test]
+    fn test_components_keep_high_dynamic_range() {
+        // Una luz más fuerte que el blanco no se recorta en los pases de luz
+        let dim = scene_sample(Vec3::new(0.0, 0.0, -1.0), 1.0);
+        let bright = scene_sample(Vec3::new(0.0, 0.0, -1.0), 4.0);
+        assert!(bright[Aov::Diffuse.index()].x > 1.0);
+        assert!((bright[Aov::Diffuse.index()] - dim[Aov::Diffuse.index()] * 4.0).norm() < 1e-4);
+        assert!(bright[Aov::Beauty.index()].x > 1.0);
+    }
+
+    #[test]
+    fn test_sky_has_no_geometry() {
+        let sample = scene_sample(Vec3::new(0.0, 1.0, 0.0), 1.0);
+        assert_eq!(sample[Aov::Depth.index()].x, f32::INFINITY);
+        assert_eq!(sample[Aov::ObjectId.index()].x, 0.0);
+        assert_eq!(sample[Aov::Normal.index()], Vec3::zeros());
+        assert_eq!(sample[Aov::Albedo.index()], Vec3::repeat(SKY_ALBEDO));
+    }
+
+    #[test]
+    fn test_png_visualization() {
+        let mut passes = RenderPasses::new(2, 1);
+        passes.layer_mut(Aov::Depth).copy_from_slice(&[Vec3::repeat(2.0), Vec3::repeat(f32::INFINITY)]);
+        passes.layer_mut(Aov::Normal)[0] = Vec3::new(0.0, 0.0, 1.0);
+        assert_eq!(passes.to_rgb8(Aov::Depth), vec![255, 255, 255, 0, 0, 0]);
+        assert_eq!(&passes.to_rgb8(Aov::Normal)[..3], &[128, 128, 255]);
+        assert_ne!(id_color(1).to_hex(), id_color(2).to_hex());
+        assert_eq!(id_color(0).to_hex(), 0);
+    }
+
+    #[test]
+    fn test_exr_has_one_layer_per_pass() {
+        let path = std::env::temp_dir().join(format!("aov_test_{}.exr", std::process::id()));
+        let passes = RenderPasses::new(4, 3);
+        passes.save_exr(&path).unwrap();
+
+        let meta = exr::meta::MetaData::read_from_file(&path, false).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let names: Vec<String> = meta.headers.iter()
+            .map(|header| header.own_attributes.layer_name.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(names.len(), Aov::ALL.len());
+        assert!(names.contains(&"depth".to_string()));
+    }
+}
@@ -1,5 +1,6 @@
 use nalgebra_glm::Vec3;
 use std::f32::consts::PI;
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub eye: Vec3,
     pub center: Vec3,
@@ -24,12 +25,9 @@ impl Camera {
         let right = forward.cross(&self.up).normalize();
         let up = right.cross(&forward); // Ya está normalizado
     
-        let rotated =
-            vector.x * right +
-            vector.y * up -
-            vector.z * forward;
-    
-        rotated
+        vector.x * right +
+        vector.y * up -
+        vector.z * forward
     }    
 
     pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Uso: Raytracing [opciones]
+
+Sin opciones se abre la ventana interactiva. Con --output o --exr se renderiza
+un solo cuadro sin ventana y se guarda en disco.
+
+  --output <archivo.png>   Guarda la imagen final
+  --passes                 Guarda también cada pase como <archivo>.<pase>.png
+  --exr <archivo.exr>      Guarda todos los pases como capas de un EXR
+                           (--passes y --exr solo con el trazador de Whitted)
+  --samples <n>            Muestras por píxel del trazador de caminos (0 = Whitted)
+  --threshold <error>      Muestreo adaptativo: cada píxel deja de recibir muestras
+                           cuando su error relativo baja de este valor (p. ej. 0.02);
+                           --samples pasa a ser el máximo por píxel
+  --min-samples <n>        Muestras mínimas por píxel con --threshold (4 por defecto)
+  --heatmap <archivo.png>  Guarda un mapa de calor de las muestras por píxel
+  --denoise                Filtra el ruido de la imagen final con las guías de albedo,
+                           normal y profundidad
+  --stats                  Muestra el tiempo de render, los rayos por segundo y los
+                           demás datos del HUD
+  --time <segundos>        Momento del ciclo de día que se renderiza
+  --save-materials <archivo>
+                           Guarda la paleta de materiales leída (cada material solo
+                           con lo que cambia respecto de su base)
+  --size <ancho>x<alto>    Resolución de la imagen (800x600 por defecto)";
+
+// Opciones de la línea de comandos
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub output: Option<PathBuf>,
+    pub passes: bool,
+    pub exr: Option<PathBuf>,
+    pub samples: u32,
+    pub threshold: Option<f32>,
+    pub min_samples: u32,
+    pub heatmap: Option<PathBuf>,
+    pub denoise: bool,
+    pub stats: bool,
+    pub time: f32,
+    pub save_materials: Option<PathBuf>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            output: None,
+            passes: false,
+            exr: None,
+            samples: 0,
+            threshold: None,
+            min_samples: 4,
+            heatmap: None,
+            denoise: false,
+            stats: false,
+            time: 2.5, // Mediodía, con el sol en lo más alto, en el ciclo de 10 segundos
+            save_materials: None,
+            width: 800,
+            height: 600,
+        }
+    }
+}
+
+impl Options {
+    // Lee las opciones (sin el nombre del programa). El error describe la opción inválida.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("falta el valor de {}", name));
+            match arg.as_str() {
+                "--output" => options.output = Some(PathBuf::from(value("--output")?)),
+                "--passes" => options.passes = true,
+                "--exr" => options.exr = Some(PathBuf::from(value("--exr")?)),
+                "--samples" => {
+                    let text = value("--samples")?;
+                    options.samples = text.parse().map_err(|_| format!("número de muestras inválido: {}", text))?;
+                }
+                "--threshold" => {
+                    let text = value("--threshold")?;
+                    let threshold = text.parse().ok().filter(|&threshold: &f32| threshold >= 0.0);
+                    options.threshold = Some(threshold.ok_or(format!("umbral inválido: {}", text))?);
+                }
+                "--min-samples" => {
+                    let text = value("--min-samples")?;
+                    options.min_samples = text.parse().map_err(|_| format!("número de muestras inválido: {}", text))?;
+                }
+                "--heatmap" => options.heatmap = Some(PathBuf::from(value("--heatmap")?)),
+                "--denoise" => options.denoise = true,
+                "--stats" => options.stats = true,
+                "--time" => {
+                    let text = value("--time")?;
+                    options.time = text.parse().map_err(|_| format!("tiempo inválido: {}", text))?;
+                }
+                "--save-materials" => options.save_materials = Some(PathBuf::from(value("--save-materials")?)),
+                "--size" => {
+                    let text = value("--size")?;
+                    let (width, height) = text
+                        .split_once('x')
+                        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                        .filter(|&(width, height): &(usize, usize)| width > 0 && height > 0)
+                        .ok_or(format!("resolución inválida: {}", text))?;
+                    options.width = width;
+                    options.height = height;
+                }
+                other => return Err(format!("opción desconocida: {}", other)),
+            }
+        }
+
+        if options.passes && options.output.is_none() {
+            return Err("--passes necesita --output".to_string());
+        }
+        // Los pases de iluminación (difuso, especular...) salen del trazador de Whitted: junto a
+        // una imagen final del trazador de caminos no sumarían esa imagen
+        if options.samples > 0 && (options.passes || options.exr.is_some()) {
+            return Err("--passes y --exr no se pueden combinar con --samples".to_string());
+        }
+        if (options.threshold.is_some() || options.heatmap.is_some()) && options.samples == 0 {
+            return Err("--threshold y --heatmap necesitan --samples".to_string());
+        }
+        Ok(options)
+    }
+
+    // Renderizar un cuadro y salir en lugar de abrir la ventana
+    pub fn is_headless(&self) -> bool {
+        self.output.is_some() || self.exr.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_no_arguments_opens_the_window() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options, Options::default());
+        assert!(!options.is_headless());
+    }
+
+    #[test]
+    fn test_headless_options() {
+        let options = parse(&["--output", "out.png", "--samples", "16", "--denoise", "--stats", "--size", "320x240", "--time", "0.5",
+            "--save-materials", "palette.txt"]).unwrap();
+        assert!(options.is_headless());
+        assert_eq!(options.output, Some(PathBuf::from("out.png")));
+        assert!(!options.passes);
+        assert_eq!(options.samples, 16);
+        assert!(options.denoise);
+        assert!(options.stats);
+        assert_eq!((options.width, options.height), (320, 240));
+        assert_eq!(options.time, 0.5);
+        assert_eq!(options.save_materials, Some(PathBuf::from("palette.txt")));
+    }
+
+    #[test]
+    fn test_adaptive_sampling_options() {
+        let options = parse(&["--output", "out.png", "--samples", "256", "--threshold", "0.02", "--min-samples", "8", "--heatmap", "spp.png"]).unwrap();
+        assert_eq!(options.threshold, Some(0.02));
+        assert_eq!(options.min_samples, 8);
+        assert_eq!(options.heatmap, Some(PathBuf::from("spp.png")));
+        assert!(parse(&["--output", "out.png", "--threshold", "0.02"]).is_err());
+        assert!(parse(&["--output", "out.png", "--samples", "16", "--threshold", "-1"]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(parse(&["--samples", "muchas"]).is_err());
+        assert!(parse(&["--size", "0x10"]).is_err());
+        assert!(parse(&["--output"]).is_err());
+        assert!(parse(&["--passes"]).is_err());
+        assert!(parse(&["--output", "out.png", "--passes", "--samples", "16"]).is_err());
+        assert!(parse(&["--exr", "out.exr", "--samples", "16"]).is_err());
+        assert!(parse(&["--output", "out.png", "--passes", "--exr", "out.exr"]).is_ok());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use nalgebra_glm::Vec3;
+use crate::fog::henyey_greenstein;
+use crate::light::Light;
+use crate::noise::Noise;
+
+// Las nubes más lejanas que esto se funden con el cielo
+const MAX_CLOUD_DISTANCE: f32 = 150.0;
+// Dispersión hacia adelante de las gotas de agua: el borde de las nubes brilla a contraluz
+const CLOUD_ANISOTROPY: f32 = 0.3;
+// Fracción de la luz del cielo que ilumina las nubes por debajo
+const CLOUD_AMBIENT: f32 = 0.7;
+
+// Capa de nubes volumétricas entre dos alturas. La densidad sale de ruido fbm con semilla,
+// arrastrado por el viento, así que la misma semilla produce siempre el mismo cielo.
+#[derive(Debug, Clone)]
+pub struct CloudLayer {
+    noise: Noise,
+    pub altitude: f32,  // Altura de la base de la capa
+    pub thickness: f32, // Espesor vertical de la capa
+    pub coverage: f32,  // Fracción del cielo cubierta, entre 0 y 1
+    pub density: f32,   // Coeficiente de extinción en el interior de una nube
+    pub scale: f32,     // Frecuencia del ruido: valores pequeños dan nubes más grandes
+    pub wind: Vec3,     // Velocidad (unidades por segundo) con la que se desplazan las nubes
+    pub steps: u32,     // Muestras a lo largo de un rayo que cruza la capa
+    pub light_steps: u32, // Muestras de los rayos hacia la luz (sombras)
+}
+
+impl CloudLayer {
+    pub fn new(seed: u64, altitude: f32, thickness: f32) -> Self {
+        CloudLayer {
+            noise: Noise::new(seed),
+            altitude,
+            thickness,
+            coverage: 0.5,
+            density: 1.5,
+            scale: 0.25,
+            wind: Vec3::new(0.4, 0.0, 0.15),
+            steps: 24,
+            light_steps: 6,
+        }
+    }
+
+    pub fn with_coverage(mut self, coverage: f32) -> Self {
+        self.coverage = coverage.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_wind(mut self, wind: Vec3) -> Self {
+        self.wind = wind;
+        self
+    }
+
+    // Coeficiente de extinción en un punto, en el instante `time` (segundos)
+    pub fn density_at(&self, point: &Vec3, time: f32) -> f32 {
+        let height = (point.y - self.altitude) / self.thickness;
+        if !(0.0..=1.0).contains(&height) || self.coverage <= 0.0 {
+            return 0.0;
+        }
+
+        // El fbm queda casi siempre en [-0.5, 0.5]; lo llevamos a [0, 1] y recortamos
+        // lo que no supera el umbral de cobertura, de modo que quedan nubes sueltas
+        let drifted = (point - self.wind * time) * self.scale;
+        let value = (self.noise.fbm(&drifted, 4, 2.0, 0.5) + 0.5).clamp(0.0, 1.0);
+        let cloud = ((value - (1.0 - self.coverage)) / self.coverage).max(0.0);
+
+        // Bordes superior e inferior redondeados
+        let profile = 4.0 * height * (1.0 - height);
+        self.density * cloud * profile
+    }
+
+    // Tramo del rayo (distancias desde el origen) que está dentro de la capa
+    fn slab(&self, origin: &Vec3, direction: &Vec3) -> Option<(f32, f32)> {
+        let top = self.altitude + self.thickness;
+        if direction.y.abs() < 1e-6 {
+            let inside = origin.y >= self.altitude && origin.y <= top;
+            return if inside { Some((0.0, MAX_CLOUD_DISTANCE)) } else { None };
+        }
+
+        let to_base = (self.altitude - origin.y) / direction.y;
+        let to_top = (top - origin.y) / direction.y;
+        let near = to_base.min(to_top).max(0.0);
+        let far = to_base.max(to_top);
+        if far <= near { None } else { Some((near, far)) }
+    }
+
+    // Fracción de la luz que atraviesa las nubes a lo largo de `distance` unidades del rayo
+    pub fn transmittance(&self, origin: &Vec3, direction: &Vec3, distance: f32, time: f32) -> f32 {
+        let Some((near, far)) = self.slab(origin, direction) else {
+            return 1.0;
+        };
+        let far = far.min(distance);
+        if far <= near {
+            return 1.0;
+        }
+
+        let step = (far - near) / self.light_steps as f32;
+        let optical_depth: f32 = (0..self.light_steps)
+            .map(|index| {
+                let point = origin + direction * (near + (index as f32 + 0.5) * step);
+                self.density_at(&point, time)
+            })
+            .sum::<f32>() * step;
+        (-optical_depth).exp()
+    }
+
+    // Radiancia (lineal, 1.0 = 255) del cielo en la dirección del rayo: el fondo tapado
+    // por las nubes que cruza, iluminadas por el sol y por el propio cielo
+    pub fn sky(&self, origin: &Vec3, direction: &Vec3, time: f32, sun: Option<&Light>, background: Vec3) -> Vec3 {
+        let Some((near, far)) = self.slab(origin, direction) else {
+            return background;
+        };
+        if near >= MAX_CLOUD_DISTANCE {
+            return background;
+        }
+
+        let far = far.min(near + self.thickness * 4.0);
+        let step = (far - near) / self.steps as f32;
+        let ambient = background * CLOUD_AMBIENT;
+
+        let mut transmittance = 1.0;
+        let mut scattered = Vec3::zeros();
+        for index in 0..self.steps {
+            let point = origin + direction * (near + (index as f32 + 0.5) * step);
+            let density = self.density_at(&point, time);
+            if density <= 0.0 {
+                continue;
+            }
+
+            // Autosombra: cuánta luz del sol llega a este punto a través de la propia nube
+            let mut light = ambient;
+            if let Some(sun) = sun.filter(|sun| sun.intensity > 0.0) {
+                let to_sun = (sun.position - point).normalize();
+                let sunlight = self.transmittance(&point, &to_sun, f32::INFINITY, time)
+                    * henyey_greenstein(CLOUD_ANISOTROPY, direction.dot(&to_sun));
+                light += sun.color.to_vec3() * (sun.intensity * sunlight);
+            }
+
+            let step_transmittance = (-density * step).exp();
+            scattered += light * (transmittance * (1.0 - step_transmittance));
+            transmittance *= step_transmittance;
+            if transmittance < 0.01 {
+                break;
+            }
+        }
+
+        // Las nubes del horizonte se desvanecen en el color del cielo
+        let fade = 1.0 - near / MAX_CLOUD_DISTANCE;
+        let clouds = background * transmittance + scattered;
+        background * (1.0 - fade) + clouds * fade
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_density_is_confined_to_the_layer() {
+        let clouds = CloudLayer::new(3, 10.0, 2.0).with_coverage(1.0);
+        assert_eq!(clouds.density_at(&Vec3::new(0.3, 9.0, 0.7), 0.0), 0.0);
+        assert_eq!(clouds.density_at(&Vec3::new(0.3, 12.5, 0.7), 0.0), 0.0);
+        // Con cobertura total hay nube en el centro de la capa
+        assert!(clouds.density_at(&Vec3::new(0.3, 11.0, 0.7), 0.0) > 0.0);
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_sky() {
+        let first = CloudLayer::new(42, 10.0, 2.0);
+        let second = CloudLayer::new(42, 10.0, 2.0);
+        let other = CloudLayer::new(43, 10.0, 2.0);
+
+        let points: Vec<Vec3> = (0..32).map(|i| Vec3::new(i as f32 * 0.7, 11.0, i as f32 * 1.3)).collect();
+        let densities = |clouds: &CloudLayer| points.iter().map(|p| clouds.density_at(p, 0.0)).collect::<Vec<_>>();
+        assert_eq!(densities(&first), densities(&second));
+        assert_ne!(densities(&first), densities(&other));
+    }
+
+    #[test]
+    fn test_wind_moves_the_clouds() {
+        let clouds = CloudLayer::new(7, 10.0, 2.0).with_coverage(1.0).with_wind(Vec3::new(1.0, 0.0, 0.0));
+        let point = Vec3::new(2.0, 11.0, 3.0);
+        // Lo que a los 5 segundos está en `point` estaba antes 5 unidades contra el viento
+        let later = clouds.density_at(&point, 5.0);
+        let earlier = clouds.density_at(&(point - Vec3::new(5.0, 0.0, 0.0)), 0.0);
+        assert!((later - earlier).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_transmittance_only_counts_the_layer() {
+        let clouds = CloudLayer::new(5, 10.0, 2.0).with_coverage(1.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let origin = Vec3::new(0.5, 0.0, 0.5);
+        // Una luz por debajo de la capa no queda tapada
+        assert_eq!(clouds.transmittance(&origin, &up, 5.0, 0.0), 1.0);
+        let through = clouds.transmittance(&origin, &up, f32::INFINITY, 0.0);
+        assert!(through < 1.0 && through > 0.0);
+        // Mirando hacia abajo no hay nubes
+        assert_eq!(clouds.transmittance(&origin, &-up, f32::INFINITY, 0.0), 1.0);
+    }
+}
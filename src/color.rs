@@ -1,5 +1,6 @@
 use std::fmt;
 use std::ops::{Add, Mul};
+use nalgebra_glm::Vec3;
 
 // Definimos la estructura Color
 #[derive(Debug, Copy, Clone)]
@@ -22,12 +23,35 @@ impl Color {
 
     pub fn black() -> Color {
         Color {
-            red: 0 as u8,
-            green: 0 as u8,
-            blue: 0 as u8,
+            red: 0,
+            green: 0,
+            blue: 0,
         }
     }
 
+    // Multiplica cada canal por su propio factor (filtros, absorción...)
+    pub fn modulate(self, factors: [f32; 3]) -> Color {
+        Color {
+            red: (self.red as f32 * factors[0]).clamp(0.0, 255.0) as u8,
+            green: (self.green as f32 * factors[1]).clamp(0.0, 255.0) as u8,
+            blue: (self.blue as f32 * factors[2]).clamp(0.0, 255.0) as u8,
+        }
+    }
+
+    // Color a partir de valores lineales en [0, 1] (los mayores se saturan)
+    pub fn from_vec3(color: Vec3) -> Color {
+        Color::new(
+            (color.x * 255.0).round() as i32,
+            (color.y * 255.0).round() as i32,
+            (color.z * 255.0).round() as i32,
+        )
+    }
+
+    // Canales en [0, 1], para operar con precisión de punto flotante
+    pub fn to_vec3(self) -> Vec3 {
+        Vec3::new(self.red as f32, self.green as f32, self.blue as f32) / 255.0
+    }
+
     // Método para crear una instancia de Color a partir de un valor hexadecimal
     pub fn from_hex(hex: u32) -> Color {
         Color {
@@ -38,7 +62,7 @@ impl Color {
     }
 
     // Método para convertir Color a hexadecimal
-    pub fn to_hex(&self) -> u32 {
+    pub fn to_hex(self) -> u32 {
         ((self.red as u32) << 16) | ((self.green as u32) << 8) | (self.blue as u32)
     }
 }
@@ -112,6 +136,13 @@ mod tests {
         assert_eq!(result.blue, 255);
     }
 
+    #[test]
+    fn test_vec3_round_trip() {
+        let color = Color::new(10, 128, 255);
+        assert_eq!(Color::from_vec3(color.to_vec3()).to_hex(), color.to_hex());
+        assert_eq!(Color::from_vec3(Vec3::new(2.0, -1.0, 0.5)).to_hex(), 0xFF0080);
+    }
+
     #[test]
     fn test_mul() {
         let color = Color::new(100, 150, 200);
@@ -121,6 +152,15 @@ mod tests {
         assert_eq!(result.blue, 100);
     }
 
+    #[test]
+    fn test_modulate() {
+        let color = Color::new(100, 150, 200);
+        let result = color.modulate([0.5, 1.0, 2.0]);
+        assert_eq!(result.red, 50);
+        assert_eq!(result.green, 150);
+        assert_eq!(result.blue, 255);
+    }
+
     #[test]
     fn test_display() {
         let color = Color::new(255, 0, 255);
@@ -1,41 +1,132 @@
-use crate::materials::Material;
 use nalgebra_glm::{Vec3, min2, max2};
 use crate::ray_intersect::{RayIntersect, Intersect};
 
+// Caras del cubo, en el mismo orden en que se guardan sus materiales
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,   // -X
+    Right,  // +X
+    Bottom, // -Y
+    Top,    // +Y
+    Back,   // -Z
+    Front,  // +Z
+}
+
+impl CubeFace {
+    pub fn normal(self) -> Vec3 {
+        match self {
+            CubeFace::Left => Vec3::new(-1.0, 0.0, 0.0),
+            CubeFace::Right => Vec3::new(1.0, 0.0, 0.0),
+            CubeFace::Bottom => Vec3::new(0.0, -1.0, 0.0),
+            CubeFace::Top => Vec3::new(0.0, 1.0, 0.0),
+            CubeFace::Back => Vec3::new(0.0, 0.0, -1.0),
+            CubeFace::Front => Vec3::new(0.0, 0.0, 1.0),
+        }
+    }
+}
+
+// Forma de proyectar las texturas sobre las caras del cubo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UvMapping {
+    Stretch,                    // Una sola copia de la textura estirada sobre toda la cara
+    World { block_size: f32 },  // La textura se repite una vez por cada bloque del mundo
+}
+
 pub struct Cube {
     pub min: Vec3, // Una esquina del cubo
     pub max: Vec3, // La esquina opuesta del cubo
-    pub material: Material,
+    pub materials: [usize; 6], // Un material por cara (índices en MaterialLibrary), indexado por CubeFace
+    pub uv_mapping: UvMapping,
 }
 
 impl Cube {
-    // Obtén las coordenadas UV para el cubo
-    fn get_uv(&self, point: &Vec3) -> (f32, f32) {
-        // Determina qué cara del cubo se está intersectando
-        let mut u = 0.0;
-        let mut v = 0.0;
-
-        if point.x == self.min.x { // Cara izquierda
-            u = (point.z - self.min.z) / (self.max.z - self.min.z);
-            v = (point.y - self.min.y) / (self.max.y - self.min.y);
-        } else if point.x == self.max.x { // Cara derecha
-            u = (point.z - self.min.z) / (self.max.z - self.min.z);
-            v = (point.y - self.min.y) / (self.max.y - self.min.y);
-        } else if point.y == self.min.y { // Cara inferior
-            u = (point.x - self.min.x) / (self.max.x - self.min.x);
-            v = (point.z - self.min.z) / (self.max.z - self.min.z);
-        } else if point.y == self.max.y { // Cara superior
-            u = (point.x - self.min.x) / (self.max.x - self.min.x);
-            v = (point.z - self.min.z) / (self.max.z - self.min.z);
-        } else if point.z == self.min.z { // Cara trasera
-            u = (point.x - self.min.x) / (self.max.x - self.min.x);
-            v = (point.y - self.min.y) / (self.max.y - self.min.y);
-        } else if point.z == self.max.z { // Cara delantera
-            u = (point.x - self.min.x) / (self.max.x - self.min.x);
-            v = (point.y - self.min.y) / (self.max.y - self.min.y);
+    // Cubo con el mismo material en las seis caras
+    pub fn new(min: Vec3, max: Vec3, material: usize) -> Self {
+        Cube {
+            min,
+            max,
+            materials: [material; 6],
+            uv_mapping: UvMapping::Stretch,
+        }
+    }
+
+    // Bloque estilo Minecraft: un material arriba, otro en los lados y otro abajo
+    pub fn new_block(min: Vec3, max: Vec3, top: usize, side: usize, bottom: usize) -> Self {
+        Cube::new(min, max, side)
+            .with_face_material(CubeFace::Top, top)
+            .with_face_material(CubeFace::Bottom, bottom)
+    }
+
+    // Reemplaza el material de una sola cara
+    pub fn with_face_material(mut self, face: CubeFace, material: usize) -> Self {
+        self.materials[face as usize] = material;
+        self
+    }
+
+    pub fn material(&self, face: CubeFace) -> usize {
+        self.materials[face as usize]
+    }
+
+    // Determina qué cara contiene el punto, con tolerancia para errores de punto flotante
+    fn face_at(&self, point: &Vec3) -> CubeFace {
+        if (point.x - self.min.x).abs() < 1e-4 {
+            CubeFace::Left
+        } else if (point.x - self.max.x).abs() < 1e-4 {
+            CubeFace::Right
+        } else if (point.y - self.min.y).abs() < 1e-4 {
+            CubeFace::Bottom
+        } else if (point.y - self.max.y).abs() < 1e-4 {
+            CubeFace::Top
+        } else if (point.z - self.min.z).abs() < 1e-4 {
+            CubeFace::Back
+        } else {
+            CubeFace::Front
+        }
+    }
+
+    // Cambia la forma en que se proyecta la textura sobre las caras
+    pub fn with_uv_mapping(mut self, uv_mapping: UvMapping) -> Self {
+        self.uv_mapping = uv_mapping;
+        self
+    }
+
+    // Ejes de la cara que corresponden a u y v (v siempre "hacia arriba" en los lados)
+    fn uv_axes(face: CubeFace) -> (usize, usize) {
+        match face {
+            CubeFace::Left | CubeFace::Right => (2, 1),
+            CubeFace::Bottom | CubeFace::Top => (0, 2),
+            CubeFace::Back | CubeFace::Front => (0, 1),
         }
+    }
+
+    // Cuánto cambian las UV por unidad de distancia sobre la cara
+    fn uv_density(&self, face: CubeFace) -> f32 {
+        match self.uv_mapping {
+            UvMapping::Stretch => {
+                let (u_axis, v_axis) = Cube::uv_axes(face);
+                let extent = (self.max[u_axis] - self.min[u_axis]).min(self.max[v_axis] - self.min[v_axis]);
+                1.0 / extent
+            }
+            UvMapping::World { block_size } => 1.0 / block_size,
+        }
+    }
+
+    // Obtén las coordenadas UV para el punto sobre la cara indicada
+    fn get_uv(&self, point: &Vec3, face: CubeFace) -> (f32, f32) {
+        let (u_axis, v_axis) = Cube::uv_axes(face);
 
-        (u, v)
+        match self.uv_mapping {
+            UvMapping::Stretch => {
+                let u = (point[u_axis] - self.min[u_axis]) / (self.max[u_axis] - self.min[u_axis]);
+                let v = (point[v_axis] - self.min[v_axis]) / (self.max[v_axis] - self.min[v_axis]);
+                (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))
+            }
+            UvMapping::World { block_size } => {
+                // Una unidad UV por bloque, anclada al origen del mundo; el modo de
+                // envoltura del material se encarga de repetir la textura
+                (point[u_axis] / block_size, point[v_axis] / block_size)
+            }
+        }
     }
 }
 
@@ -61,34 +152,117 @@ impl RayIntersect for Cube {
         let t_near = t1.x.max(t1.y).max(t1.z);
         let t_far = t2.x.min(t2.y).min(t2.z);
 
-        // Si el rayo no intersecta el cubo
-        if t_near < 0.0 || t_near > t_far {
+        // Si el rayo no intersecta el cubo (o el cubo quedó detrás del rayo)
+        if t_far < 0.0 || t_near > t_far {
             return Intersect::empty();
         }
 
+        // Si el origen está dentro del cubo, el rayo lo abandona por la cara lejana
+        let t_hit = if t_near >= 0.0 { t_near } else { t_far };
+
         // Calcular el punto de intersección y normal
-        let intersection_point = ray_origin + ray_direction * t_near;
-
-        // Para encontrar la normal de la cara intersectada
-        let normal = if (intersection_point.x - self.min.x).abs() < 1e-4 {
-            Vec3::new(-1.0, 0.0, 0.0)
-        } else if (intersection_point.x - self.max.x).abs() < 1e-4 {
-            Vec3::new(1.0, 0.0, 0.0)
-        } else if (intersection_point.y - self.min.y).abs() < 1e-4 {
-            Vec3::new(0.0, -1.0, 0.0)
-        } else if (intersection_point.y - self.max.y).abs() < 1e-4 {
-            Vec3::new(0.0, 1.0, 0.0)
-        } else if (intersection_point.z - self.min.z).abs() < 1e-4 {
-            Vec3::new(0.0, 0.0, -1.0)
-        } else {
-            Vec3::new(0.0, 0.0, 1.0)
-        };
+        let intersection_point = ray_origin + ray_direction * t_hit;
 
-        let normal = normal.normalize();
+        // La cara intersectada define tanto la normal como el material
+        let face = self.face_at(&intersection_point);
+        let normal = face.normal();
         // Calcular las coordenadas UV en la cara donde se dio la intersección
-        let (u, v) = self.get_uv(&intersection_point);
+        let (u, v) = self.get_uv(&intersection_point, face);
 
         // Retornar la intersección con toda la información
-        Intersect::new(intersection_point, normal, t_near, self.material, u, v)
+        let mut intersect = Intersect::new(intersection_point, normal, t_hit, self.material(face), u, v);
+        intersect.uv_density = self.uv_density(face);
+        let (u_axis, v_axis) = Cube::uv_axes(face);
+        intersect.tangent = Vec3::zeros();
+        intersect.tangent[u_axis] = 1.0;
+        intersect.bitangent = Vec3::zeros();
+        intersect.bitangent[v_axis] = 1.0;
+        intersect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_faces_use_their_own_material() {
+        let cube = Cube::new_block(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            10,
+            20,
+            30,
+        );
+
+        let top = cube.ray_intersect(&Vec3::new(0.0, 5.0, 0.0), &Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(top.material_id, 10);
+        assert_eq!(top.normal, Vec3::new(0.0, 1.0, 0.0));
+
+        let side = cube.ray_intersect(&Vec3::new(5.0, 0.0, 0.0), &Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(side.material_id, 20);
+
+        let bottom = cube.ray_intersect(&Vec3::new(0.0, -5.0, 0.0), &Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(bottom.material_id, 30);
+    }
+
+    #[test]
+    fn test_single_face_override() {
+        let cube = Cube::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 10)
+            .with_face_material(CubeFace::Front, 99);
+
+        let front = cube.ray_intersect(&Vec3::new(0.5, 0.5, 5.0), &Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(front.material_id, 99);
+
+        let back = cube.ray_intersect(&Vec3::new(0.5, 0.5, -5.0), &Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(back.material_id, 10);
+    }
+
+    #[test]
+    fn test_tangent_frame_follows_uv() {
+        let cube = Cube::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 10);
+        let hit = cube.ray_intersect(&Vec3::new(5.0, 0.5, 0.5), &Vec3::new(-1.0, 0.0, 0.0));
+
+        // En la cara derecha u crece con z y v crece hacia arriba
+        assert_eq!(hit.tangent, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit.bitangent, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(hit.tangent.dot(&hit.normal), 0.0);
+    }
+
+    #[test]
+    fn test_ray_from_inside_hits_exit_face() {
+        let cube = Cube::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0), 10);
+
+        let hit = cube.ray_intersect(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(1.0, 0.0, 0.0));
+        assert!(hit.is_intersecting);
+        assert!((hit.distance - 1.0).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec3::new(1.0, 0.0, 0.0));
+
+        // Un cubo detrás del rayo no se intersecta
+        let behind = cube.ray_intersect(&Vec3::new(3.0, 0.0, 0.0), &Vec3::new(1.0, 0.0, 0.0));
+        assert!(!behind.is_intersecting);
+    }
+
+    #[test]
+    fn test_stretch_uv_tolerates_float_error() {
+        let cube = Cube::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(4.0, 0.5, 1.0), 10);
+
+        // El rayo llega a la cara superior con un error de redondeo en y
+        let hit = cube.ray_intersect(&Vec3::new(3.0, 1.3, 0.25), &Vec3::new(0.0, -0.7, 0.0));
+        assert!((hit.u - 0.75).abs() < 1e-4);
+        assert!((hit.v - 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_world_uv_repeats_per_block() {
+        let cube = Cube::new(Vec3::new(-2.5, -1.0, -1.5), Vec3::new(1.5, -0.5, 0.0), 10)
+            .with_uv_mapping(UvMapping::World { block_size: 0.5 });
+
+        // Dos puntos separados por exactamente un bloque quedan a una repetición de distancia
+        let a = cube.ray_intersect(&Vec3::new(-2.2, 3.0, -0.4), &Vec3::new(0.0, -1.0, 0.0));
+        let b = cube.ray_intersect(&Vec3::new(-1.7, 3.0, -0.4), &Vec3::new(0.0, -1.0, 0.0));
+        assert!((a.u + 4.4).abs() < 1e-4);
+        assert!((b.u - a.u - 1.0).abs() < 1e-4);
+        assert!((a.v - b.v).abs() < 1e-4);
     }
 }
@@ -0,0 +1,228 @@
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+use crate::aov::{Aov, RenderPasses, SKY_ALBEDO};
+use crate::ray_intersect::RayCone;
+use crate::tracing::{RenderContext, flush_all_ray_counts, scene_intersect};
+
+// Pesos del B-spline cúbico que se aplica en cada paso de la transformada à-trous
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+// Albedo mínimo al separar la iluminación de la textura, para no dividir por cero
+const MIN_ALBEDO: f32 = 0.02;
+
+// Datos del primer impacto de cada píxel que guían al filtro: no se mezclan píxeles que
+// pertenecen a superficies distintas aunque su color (ruidoso) se parezca
+pub struct Guides {
+    pub width: usize,
+    pub height: usize,
+    pub albedo: Vec<Vec3>,
+    pub normal: Vec<Vec3>,
+    pub depth: Vec<f32>, // Infinito donde se ve el cielo
+}
+
+impl Guides {
+    // Lanza un rayo por píxel sin sombrear nada: mucho más barato que un cuadro
+    pub fn render<F>(width: usize, height: usize, eye: &Vec3, direction: F, context: &RenderContext, cone: RayCone) -> Self
+    where
+        F: Fn(f32, f32) -> Vec3 + Sync,
+    {
+        let samples: Vec<(Vec3, Vec3, f32)> = (0..width * height)
+            .into_par_iter()
+            .map(|index| {
+                let ray_direction = direction((index % width) as f32, (index / width) as f32);
+                let intersect = scene_intersect(eye, &ray_direction, context, cone);
+                if !intersect.is_intersecting {
+                    return (Vec3::repeat(SKY_ALBEDO), Vec3::zeros(), f32::INFINITY);
+                }
+
+                let material = context.materials.get(intersect.material_id);
+                let footprint = cone.uv_footprint(&intersect, &ray_direction);
+                let facing = if ray_direction.dot(&intersect.normal) < 0.0 { 1.0 } else { -1.0 };
+                let normal = material.shading_normal(&intersect, footprint, context.time, context.texture_manager) * facing;
+                let albedo = material.get_diffuse_color(&intersect, footprint, context.time, context.texture_manager).to_vec3();
+                (albedo, normal, intersect.distance)
+            })
+            .collect();
+        flush_all_ray_counts();
+
+        Guides {
+            width,
+            height,
+            albedo: samples.iter().map(|sample| sample.0).collect(),
+            normal: samples.iter().map(|sample| sample.1).collect(),
+            depth: samples.iter().map(|sample| sample.2).collect(),
+        }
+    }
+
+    // Guías a partir de los pases de un render sin ventana
+    pub fn from_passes(passes: &RenderPasses) -> Self {
+        Guides {
+            width: passes.width,
+            height: passes.height,
+            albedo: passes.layer(Aov::Albedo).to_vec(),
+            normal: passes.layer(Aov::Normal).to_vec(),
+            depth: passes.layer(Aov::Depth).iter().map(|depth| depth.x).collect(),
+        }
+    }
+}
+
+// Filtro à-trous que evita los bordes (Dammertz et al. 2010): varias pasadas de un núcleo
+// de 5x5 cada vez más separado. Filtra la iluminación (color dividido por el albedo) para
+// no borrar las texturas, y pondera cada vecino por cuánto se parecen su color, normal,
+// profundidad y albedo a los del píxel central.
+#[derive(Debug, Clone, Copy)]
+pub struct Denoiser {
+    pub iterations: u32,   // Pasadas; la última abarca 2^(iterations+1) píxeles a cada lado
+    pub color_sigma: f32,  // Tolerancia de color, que se reduce a la mitad en cada pasada
+    pub normal_sigma: f32,
+    pub depth_sigma: f32,  // Tolerancia de profundidad, relativa a la distancia
+    pub albedo_sigma: f32,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser {
+            iterations: 5,
+            color_sigma: 0.8,
+            normal_sigma: 0.3,
+            depth_sigma: 0.05,
+            albedo_sigma: 0.2,
+        }
+    }
+}
+
+impl Denoiser {
+    // Imagen filtrada (valores lineales en orden de filas, del mismo tamaño que las guías)
+    pub fn denoise(&self, color: &[Vec3], guides: &Guides) -> Vec<Vec3> {
+        let safe_albedo: Vec<Vec3> = guides.albedo.iter().map(|albedo| albedo.map(|channel| channel.max(MIN_ALBEDO))).collect();
+        let mut irradiance: Vec<Vec3> = color
+            .iter()
+            .zip(&safe_albedo)
+            .map(|(color, albedo)| color.component_div(albedo))
+            .collect();
+
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let color_sigma = self.color_sigma / (1 << iteration) as f32;
+            irradiance = (0..irradiance.len())
+                .into_par_iter()
+                .map(|index| self.filter_pixel(index, step, color_sigma, &irradiance, guides))
+                .collect();
+        }
+
+        irradiance.iter().zip(&safe_albedo).map(|(irradiance, albedo)| irradiance.component_mul(albedo)).collect()
+    }
+
+    fn filter_pixel(&self, index: usize, step: i32, color_sigma: f32, irradiance: &[Vec3], guides: &Guides) -> Vec3 {
+        let (width, height) = (guides.width as i32, guides.height as i32);
+        let (x, y) = (index as i32 % width, index as i32 / width);
+        let center = irradiance[index];
+
+        let mut sum = Vec3::zeros();
+        let mut total_weight = 0.0;
+        for (ky, kernel_y) in KERNEL.iter().enumerate() {
+            for (kx, kernel_x) in KERNEL.iter().enumerate() {
+                let qx = x + (kx as i32 - 2) * step;
+                let qy = y + (ky as i32 - 2) * step;
+                if qx < 0 || qy < 0 || qx >= width || qy >= height {
+                    continue;
+                }
+                let neighbor = (qy * width + qx) as usize;
+
+                let weight = kernel_x * kernel_y
+                    * gaussian((irradiance[neighbor] - center).norm_squared(), color_sigma)
+                    * gaussian((guides.normal[neighbor] - guides.normal[index]).norm_squared(), self.normal_sigma)
+                    * gaussian((guides.albedo[neighbor] - guides.albedo[index]).norm_squared(), self.albedo_sigma)
+                    * self.depth_weight(guides.depth[index], guides.depth[neighbor]);
+                sum += irradiance[neighbor] * weight;
+                total_weight += weight;
+            }
+        }
+
+        // El píxel central siempre tiene peso, así que el total nunca es cero
+        sum / total_weight
+    }
+
+    // El cielo solo se mezcla con el cielo
+    fn depth_weight(&self, center: f32, neighbor: f32) -> f32 {
+        match (center.is_finite(), neighbor.is_finite()) {
+            (true, true) => (-(center - neighbor).abs() / (self.depth_sigma * center.max(1e-3))).exp(),
+            (false, false) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+fn gaussian(distance_squared: f32, sigma: f32) -> f32 {
+    (-distance_squared / (sigma * sigma)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn flat_guides(width: usize, height: usize) -> Guides {
+        Guides {
+            width,
+            height,
+            albedo: vec![Vec3::repeat(0.5); width * height],
+            normal: vec![Vec3::new(0.0, 1.0, 0.0); width * height],
+            depth: vec![4.0; width * height],
+        }
+    }
+
+    fn variance(image: &[Vec3]) -> f32 {
+        let mean = image.iter().sum::<Vec3>() / image.len() as f32;
+        image.iter().map(|value| (value - mean).norm_squared()).sum::<f32>() / image.len() as f32
+    }
+
+    #[test]
+    fn test_constant_image_is_unchanged() {
+        let guides = flat_guides(8, 8);
+        let image = vec![Vec3::new(0.2, 0.3, 0.4); 64];
+        for value in Denoiser::default().denoise(&image, &guides) {
+            assert!((value - Vec3::new(0.2, 0.3, 0.4)).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_noise_is_reduced() {
+        let guides = flat_guides(32, 32);
+        let mut rng = StdRng::seed_from_u64(1);
+        let noisy: Vec<Vec3> = (0..32 * 32).map(|_| Vec3::repeat(0.3 + rng.gen::<f32>() * 0.2)).collect();
+        let filtered = Denoiser::default().denoise(&noisy, &guides);
+        assert!(variance(&filtered) < variance(&noisy) * 0.2);
+    }
+
+    #[test]
+    fn test_edges_between_surfaces_are_kept() {
+        // Dos superficies con normales distintas, una oscura y otra clara
+        let mut guides = flat_guides(16, 16);
+        let image: Vec<Vec3> = (0..16 * 16)
+            .map(|index| if index % 16 < 8 { Vec3::zeros() } else { Vec3::repeat(0.5) })
+            .collect();
+        for index in 0..16 * 16 {
+            if index % 16 >= 8 {
+                guides.normal[index] = Vec3::new(1.0, 0.0, 0.0);
+            }
+        }
+
+        let filtered = Denoiser::default().denoise(&image, &guides);
+        assert!(filtered[16 * 8 + 7].x < 0.01);
+        assert!(filtered[16 * 8 + 8].x > 0.49);
+    }
+
+    #[test]
+    fn test_sky_does_not_bleed_into_surfaces() {
+        let mut guides = flat_guides(8, 1);
+        for index in 4..8 {
+            guides.depth[index] = f32::INFINITY;
+            guides.normal[index] = Vec3::zeros();
+        }
+        let image: Vec<Vec3> = (0..8).map(|index| if index < 4 { Vec3::zeros() } else { Vec3::repeat(0.5) }).collect();
+        let filtered = Denoiser::default().denoise(&image, &guides);
+        assert!(filtered.iter().all(|value| value.x.is_finite()));
+        assert!(filtered[3].x < 1e-4);
+    }
+}
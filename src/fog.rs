@@ -0,0 +1,217 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::ray_intersect::RayCone;
+use crate::tracing::{RenderContext, scene_intersect, cloud_transmittance, BACKGROUND_COLOR};
+
+// Distancia máxima de niebla que atraviesan los rayos que no golpean nada
+const MAX_FOG_DISTANCE: f32 = 30.0;
+// Tono de la niebla al amanecer y al atardecer
+const TWILIGHT_COLOR: Color = Color { red: 255, green: 150, blue: 80 };
+
+// Función de fase de Henyey–Greenstein multiplicada por 4π (vale 1 si `g` es 0, es decir,
+// si el medio dispersa igual en todas direcciones). `cos_theta` es el coseno entre la
+// dirección del rayo y la dirección hacia la luz.
+pub fn henyey_greenstein(g: f32, cos_theta: f32) -> f32 {
+    let denominator = (1.0 + g * g - 2.0 * g * cos_theta).max(1e-4);
+    (1.0 - g * g) / (denominator * denominator.sqrt())
+}
+
+// Cómo varía la densidad de la niebla en el espacio
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FogProfile {
+    // La misma densidad en todas partes
+    Homogeneous,
+    // Densidad que decae exponencialmente al subir por encima de `base_height`
+    Height { base_height: f32, falloff: f32 },
+}
+
+// Medio participativo que llena la escena: atenúa los rayos y dispersa hacia la cámara
+// la luz del sol (dispersión simple), de modo que las sombras dibujan haces de luz
+#[derive(Clone, Copy, Debug)]
+pub struct Fog {
+    pub density: f32,    // Coeficiente de extinción por unidad de distancia (a la altura base)
+    pub profile: FogProfile,
+    pub color: Color,    // Albedo de dispersión: color de la luz que devuelve la niebla
+    pub ambient: f32,    // Fracción de la luz del cielo que recibe la niebla aunque esté a la sombra
+    pub anisotropy: f32, // g de Henyey–Greenstein: positivo dispersa hacia adelante
+    pub steps: u32,      // Muestras a lo largo de cada tramo de rayo
+}
+
+impl Fog {
+    pub fn homogeneous(density: f32, color: Color) -> Self {
+        Fog {
+            density,
+            profile: FogProfile::Homogeneous,
+            color,
+            ambient: 0.5,
+            anisotropy: 0.5,
+            steps: 16,
+        }
+    }
+
+    pub fn height(density: f32, base_height: f32, falloff: f32, color: Color) -> Self {
+        Fog {
+            profile: FogProfile::Height { base_height, falloff },
+            ..Fog::homogeneous(density, color)
+        }
+    }
+
+    pub fn with_ambient(mut self, ambient: f32) -> Self {
+        self.ambient = ambient;
+        self
+    }
+
+    pub fn with_anisotropy(mut self, anisotropy: f32) -> Self {
+        self.anisotropy = anisotropy.clamp(-0.95, 0.95);
+        self
+    }
+
+    pub fn with_steps(mut self, steps: u32) -> Self {
+        self.steps = steps.max(1);
+        self
+    }
+
+    // Coeficiente de extinción en un punto
+    pub fn density_at(&self, point: &Vec3) -> f32 {
+        match self.profile {
+            FogProfile::Homogeneous => self.density,
+            FogProfile::Height { base_height, falloff } => {
+                self.density * (-falloff * (point.y - base_height).max(0.0)).exp()
+            }
+        }
+    }
+
+    // Función de fase de la niebla (ver `henyey_greenstein`)
+    pub fn phase(&self, cos_theta: f32) -> f32 {
+        henyey_greenstein(self.anisotropy, cos_theta)
+    }
+
+    // Niebla de la hora del día (`normalized_time` en [0, 1), 0 = amanecer y 0.5 = atardecer,
+    // con el sol en el horizonte): más espesa y anaranjada cuando el sol sale o se pone, más
+    // ligera y clara a mediodía y a medianoche
+    pub fn at_time_of_day(&self, normalized_time: f32) -> Fog {
+        let angle = normalized_time * 2.0 * PI;
+        let twilight = angle.cos().powi(4);
+        let color = self.color.to_vec3() * (1.0 - twilight) + TWILIGHT_COLOR.to_vec3() * twilight;
+        Fog {
+            density: self.density * (1.0 + 2.0 * twilight),
+            color: Color::from_vec3(color),
+            ..*self
+        }
+    }
+
+    // Recorre `distance` unidades del rayo a través de la niebla. Devuelve la fracción de la
+    // luz del fondo que la atraviesa y la radiancia (lineal, 1.0 = 255) que la niebla dispersa
+    // hacia el origen del rayo. El sol es la primera luz del contexto; cada muestra lanza un
+    // rayo de sombra hacia él, así que las copas de los árboles recortan haces de luz.
+    // `jitter` en [0, 1) desplaza las muestras para cambiar bandas por ruido.
+    pub fn march(
+        &self,
+        origin: &Vec3,
+        direction: &Vec3,
+        distance: f32,
+        context: &RenderContext,
+        cone: RayCone,
+        jitter: f32,
+    ) -> (f32, Vec3) {
+        let distance = distance.min(MAX_FOG_DISTANCE);
+        let step = distance / self.steps as f32;
+        let albedo = self.color.to_vec3();
+        let sky = BACKGROUND_COLOR.to_vec3() * self.ambient;
+        let sun = context.lights.first();
+
+        let mut transmittance = 1.0;
+        let mut inscattered = Vec3::zeros();
+        for index in 0..self.steps {
+            let point = origin + direction * ((index as f32 + jitter) * step);
+            let density = self.density_at(&point);
+            if density <= 0.0 {
+                continue;
+            }
+
+            let mut light = sky;
+            if let Some(sun) = sun.filter(|sun| sun.intensity > 0.0) {
+                let to_sun = sun.position - point;
+                let sun_dir = to_sun.normalize();
+                let blocker = scene_intersect(&point, &sun_dir, context, cone);
+                if !blocker.is_intersecting || blocker.distance > to_sun.norm() {
+                    let sunlight = cloud_transmittance(&point, sun, context) * self.phase(direction.dot(&sun_dir));
+                    light += sun.color.to_vec3() * (sun.intensity * sunlight);
+                }
+            }
+
+            // Luz que dispersa este tramo, atenuada por la niebla que hay delante
+            let step_transmittance = (-density * step).exp();
+            inscattered += albedo.component_mul(&light) * (transmittance * (1.0 - step_transmittance));
+            transmittance *= step_transmittance;
+        }
+
+        (transmittance, inscattered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::Light;
+    use crate::materials::TextureManager;
+    use crate::material_library::MaterialLibrary;
+
+    #[test]
+    fn test_height_fog_thins_with_altitude() {
+        let fog = Fog::height(0.2, 0.0, 1.0, Color::new(255, 255, 255));
+        assert!((fog.density_at(&Vec3::new(0.0, -3.0, 0.0)) - 0.2).abs() < 1e-6);
+        assert!((fog.density_at(&Vec3::new(0.0, 1.0, 0.0)) - 0.2 * (-1.0f32).exp()).abs() < 1e-6);
+        assert!(fog.density_at(&Vec3::new(0.0, 10.0, 0.0)) < 1e-4);
+    }
+
+    #[test]
+    fn test_phase_favors_forward_scattering() {
+        let isotropic = Fog::homogeneous(0.1, Color::black()).with_anisotropy(0.0);
+        assert!((isotropic.phase(0.3) - 1.0).abs() < 1e-6);
+
+        let forward = isotropic.with_anisotropy(0.6);
+        assert!(forward.phase(1.0) > 1.0);
+        assert!(forward.phase(-1.0) < 1.0);
+    }
+
+    #[test]
+    fn test_twilight_fog_is_thicker_and_warmer() {
+        let fog = Fog::homogeneous(0.1, Color::new(200, 210, 220));
+        let dawn = fog.at_time_of_day(0.0);
+        let noon = fog.at_time_of_day(0.25);
+        let dusk = fog.at_time_of_day(0.5);
+        assert!(dawn.density > noon.density);
+        assert!(dawn.color.red > dawn.color.blue);
+        assert!((noon.density - 0.1).abs() < 0.01);
+        // El atardecer se trata igual que el amanecer
+        assert!((dusk.density - dawn.density).abs() < 1e-4);
+        assert_eq!(dusk.color.to_hex(), dawn.color.to_hex());
+    }
+
+    #[test]
+    fn test_march_follows_beer_lambert() {
+        let texture_manager = TextureManager::new();
+        let materials = MaterialLibrary::new();
+        let lights = [Light::new(Vec3::new(0.0, 100.0, 0.0), Color::new(255, 255, 255), 1.0)];
+        let context = RenderContext {
+            objects: &[],
+            lights: &lights,
+            texture_manager: &texture_manager,
+            materials: &materials,
+            time: 0.0,
+            fog: None,
+            clouds: None,
+            sky_light: None,
+        };
+
+        let fog = Fog::homogeneous(0.1, Color::new(255, 255, 255)).with_anisotropy(0.0).with_ambient(0.0);
+        let (transmittance, inscattered) = fog.march(
+            &Vec3::zeros(), &Vec3::new(0.0, 0.0, -1.0), 5.0, &context, RayCone::from_pixel(1.0, 600.0), 0.5,
+        );
+        assert!((transmittance - (-0.5f32).exp()).abs() < 1e-4);
+        // Sol sin obstáculos, niebla blanca e isotrópica: lo que no atraviesa se dispersa
+        assert!((inscattered - Vec3::repeat(1.0 - transmittance)).norm() < 1e-3);
+    }
+}
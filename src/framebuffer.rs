@@ -1,7 +1,10 @@
 use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
 use crate::color::Color;
+use crate::font::{GLYPH_HEIGHT, GLYPH_WIDTH, glyph};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
@@ -60,6 +63,56 @@ impl Framebuffer {
         self.data[index + 2] = b;
     }
     
+    // Escribe `text` con el color actual a partir de la esquina superior izquierda (x, y).
+    // Cada píxel de la fuente ocupa `scale` x `scale` píxeles; '\n' empieza una línea nueva.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, scale: usize) {
+        let (red, green, blue) = self.current_color;
+        for (line_index, line) in text.lines().enumerate() {
+            let top = y + line_index * text_line_height(scale);
+            for (index, character) in line.chars().enumerate() {
+                let left = x + index * (GLYPH_WIDTH + 1) * scale;
+                for (row, bits) in glyph(character).iter().enumerate() {
+                    for column in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                            continue;
+                        }
+                        for dy in 0..scale {
+                            for dx in 0..scale {
+                                self.set_pixel(left + column * scale + dx, top + row * scale + dy, red, green, blue);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Multiplica por `factor` el color de un rectángulo, para oscurecer el fondo de un texto
+    pub fn shade_rect(&mut self, x: usize, y: usize, width: usize, height: usize, factor: f32) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                let index = (row * self.width + column) * 3;
+                for channel in &mut self.data[index..index + 3] {
+                    *channel = (*channel as f32 * factor) as u8;
+                }
+            }
+        }
+    }
+
+    // Copia `source`, de cualquier tamaño, estirándola al tamaño de este framebuffer: cada
+    // píxel toma el color del píxel más cercano de la imagen original
+    pub fn upscale_from(&mut self, source: &Framebuffer) {
+        for y in 0..self.height {
+            let source_y = y * source.height / self.height;
+            for x in 0..self.width {
+                let source_x = x * source.width / self.width;
+                let from = (source_y * source.width + source_x) * 3;
+                let to = (y * self.width + x) * 3;
+                self.data[to..to + 3].copy_from_slice(&source.data[from..from + 3]);
+            }
+        }
+    }
+
     pub fn to_u32_buffer(&self) -> Vec<u32> {
         let mut buffer = vec![0; self.width * self.height];
         for y in 0..self.height {
@@ -76,6 +129,46 @@ impl Framebuffer {
 
 }
 
+// Tamaño en píxeles de un texto de una línea escrito con `draw_text`
+pub fn text_width(text: &str, scale: usize) -> usize {
+    (text.chars().count() * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale
+}
+
+// Distancia entre las líneas de un texto
+pub fn text_line_height(scale: usize) -> usize {
+    (GLYPH_HEIGHT + 2) * scale
+}
+
+// Framebuffer delantero del doble búfer: el hilo que renderiza dibuja en su propio
+// framebuffer y publica copias aquí; la ventana muestra la última, terminada o a medias
+pub struct SharedFramebuffer {
+    front: Mutex<Framebuffer>,
+    version: AtomicU64, // Aumenta con cada publicación
+}
+
+impl SharedFramebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        SharedFramebuffer {
+            front: Mutex::new(Framebuffer::new(width, height)),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    pub fn publish(&self, back: &Framebuffer) {
+        self.front.lock().unwrap().data.copy_from_slice(&back.data);
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    // Copia de la última imagen publicada
+    pub fn snapshot(&self) -> Framebuffer {
+        self.front.lock().unwrap().clone()
+    }
+}
+
 impl fmt::Display for Framebuffer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for y in 0..self.height {
@@ -87,4 +180,51 @@ impl fmt::Display for Framebuffer {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_copies_the_back_buffer() {
+        let shared = SharedFramebuffer::new(2, 1);
+        let mut back = Framebuffer::new(2, 1);
+        back.set_current_color(Color::new(255, 0, 0));
+        back.point(1.0, 0.0);
+
+        assert_eq!(shared.version(), 0);
+        shared.publish(&back);
+        assert_eq!(shared.version(), 1);
+        assert_eq!(shared.snapshot().to_u32_buffer(), vec![0, 0xff0000]);
+
+        // Lo que se dibuje después en el búfer trasero no se ve hasta la siguiente publicación
+        back.point(0.0, 0.0);
+        assert_eq!(shared.snapshot().to_u32_buffer(), vec![0, 0xff0000]);
+    }
+
+    #[test]
+    fn test_upscale_repeats_nearest_pixel() {
+        let mut small = Framebuffer::new(2, 1);
+        small.set_current_color(Color::new(0, 0, 255));
+        small.point(1.0, 0.0);
+
+        let mut large = Framebuffer::new(4, 2);
+        large.upscale_from(&small);
+        assert_eq!(large.to_u32_buffer(), vec![0, 0, 0xff, 0xff, 0, 0, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let mut framebuffer = Framebuffer::new(16, 20);
+        framebuffer.set_current_color(Color::new(255, 255, 255));
+        framebuffer.draw_text(1, 1, "-\n1", 1);
+        let lit = |x: usize, y: usize| framebuffer.data[(y * 16 + x) * 3] == 255;
+
+        // El guion es la fila del medio del primer carácter
+        assert!((1..6).all(|x| lit(x, 4)));
+        assert!(!lit(1, 3) && !lit(6, 4));
+        // El '1' de la segunda línea empieza una línea más abajo
+        assert!(lit(3, 1 + text_line_height(1)));
+        assert_eq!(text_width("AB", 2), 22);
+    }
+}
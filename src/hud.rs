@@ -0,0 +1,125 @@
+use std::fmt;
+use std::time::Duration;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::framebuffer::{Framebuffer, text_line_height, text_width};
+
+// Margen entre el texto del HUD y el borde de su fondo, en píxeles de la fuente
+const HUD_PADDING: usize = 3;
+
+// Datos de rendimiento y estado del último cuadro
+#[derive(Debug, Clone)]
+pub struct RenderStats {
+    pub frame_time: Duration,
+    pub rays: u64,             // Rayos lanzados durante el cuadro
+    pub samples: Option<f32>,  // Muestras por píxel acumuladas (solo el trazador de caminos)
+    pub time_of_day: f32,      // Entre 0 y 1 dentro del ciclo; 0 = amanecer, 0.25 = mediodía
+    pub camera: Vec3,
+    pub mode: &'static str,
+    pub resolution: (usize, usize), // Resolución a la que se renderizó
+}
+
+impl RenderStats {
+    pub fn rays_per_second(&self) -> f64 {
+        self.rays as f64 / self.frame_time.as_secs_f64().max(1e-9)
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        // El ciclo empieza con el sol en el horizonte, a las 6 de la mañana
+        let minutes = ((self.time_of_day + 0.25).rem_euclid(1.0) * 24.0 * 60.0) as u32;
+        vec![
+            format!("Cuadro: {:.1} ms", self.frame_time.as_secs_f64() * 1000.0),
+            format!("Rayos/s: {}", with_prefix(self.rays_per_second())),
+            match self.samples {
+                Some(samples) => format!("Muestras: {:.1} por píxel", samples),
+                None => "Muestras: -".to_string(),
+            },
+            format!("Hora: {:02}:{:02}", minutes / 60 % 24, minutes % 60),
+            format!("Cámara: ({:.2}, {:.2}, {:.2})", self.camera.x, self.camera.y, self.camera.z),
+            format!("Modo: {}", self.mode),
+            format!("Resolución: {}x{}", self.resolution.0, self.resolution.1),
+        ]
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in self.lines() {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// Cantidad con prefijo de miles o millones, por ejemplo "1.25 M"
+fn with_prefix(value: f64) -> String {
+    if value >= 1e9 {
+        format!("{:.2} G", value / 1e9)
+    } else if value >= 1e6 {
+        format!("{:.2} M", value / 1e6)
+    } else if value >= 1e3 {
+        format!("{:.2} K", value / 1e3)
+    } else {
+        format!("{:.0}", value)
+    }
+}
+
+// Dibuja las estadísticas en la esquina superior izquierda, sobre un fondo oscurecido.
+// En imágenes pequeñas la fuente se dibuja a tamaño real y en las demás al doble.
+pub fn draw_hud(framebuffer: &mut Framebuffer, stats: &RenderStats) {
+    let scale = if framebuffer.width >= 640 { 2 } else { 1 };
+    let lines = stats.lines();
+    let padding = HUD_PADDING * scale;
+    let width = lines.iter().map(|line| text_width(line, scale)).max().unwrap_or(0) + 2 * padding;
+    let height = lines.len() * text_line_height(scale) + 2 * padding;
+
+    framebuffer.shade_rect(padding, padding, width, height, 0.35);
+    framebuffer.set_current_color(Color::new(255, 255, 255));
+    framebuffer.draw_text(2 * padding, 2 * padding, &lines.join("\n"), scale);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> RenderStats {
+        RenderStats {
+            frame_time: Duration::from_millis(250),
+            rays: 500_000,
+            samples: Some(12.0),
+            time_of_day: 0.25,
+            camera: Vec3::new(1.0, 2.0, -3.5),
+            mode: "Whitted",
+            resolution: (800, 600),
+        }
+    }
+
+    #[test]
+    fn test_stats_lines() {
+        let stats = stats();
+        assert_eq!(stats.rays_per_second(), 2_000_000.0);
+        let lines = stats.lines();
+        assert_eq!(lines[0], "Cuadro: 250.0 ms");
+        assert_eq!(lines[1], "Rayos/s: 2.00 M");
+        assert_eq!(lines[2], "Muestras: 12.0 por píxel");
+        assert_eq!(lines[3], "Hora: 12:00"); // Sol en lo más alto
+        assert_eq!(lines[4], "Cámara: (1.00, 2.00, -3.50)");
+        assert!(stats.to_string().ends_with("Resolución: 800x600\n"));
+
+        let dawn = RenderStats { time_of_day: 0.0, ..stats };
+        assert_eq!(dawn.lines()[3], "Hora: 06:00");
+    }
+
+    #[test]
+    fn test_hud_darkens_the_corner_only() {
+        let mut framebuffer = Framebuffer::new(320, 240);
+        framebuffer.set_background_color(Color::new(200, 200, 200));
+        draw_hud(&mut framebuffer, &stats());
+
+        let pixel = |x: usize, y: usize| framebuffer.data[(y * 320 + x) * 3];
+        assert!(pixel(HUD_PADDING + 1, HUD_PADDING + 1) < 100);
+        assert_eq!(pixel(319, 239), 200);
+        // Hay texto blanco dentro del recuadro
+        assert!((0..320 * 80).any(|index| framebuffer.data[index * 3] == 255));
+    }
+}
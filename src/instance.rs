@@ -0,0 +1,110 @@
+use nalgebra_glm::Vec3;
+use std::sync::Arc;
+use crate::ray_intersect::{RayIntersect, Intersect};
+use crate::transform::Transform;
+
+// Conjunto de primitivas que se comporta como un solo objeto (por ejemplo, un árbol)
+pub struct Group {
+    pub objects: Vec<Box<dyn RayIntersect>>,
+}
+
+impl Group {
+    pub fn new(objects: Vec<Box<dyn RayIntersect>>) -> Self {
+        Group { objects }
+    }
+}
+
+impl RayIntersect for Group {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let mut closest = Intersect::empty();
+        let mut zbuffer = f32::INFINITY;
+
+        for object in &self.objects {
+            let tmp = object.ray_intersect(ray_origin, ray_direction);
+            if tmp.is_intersecting && tmp.distance < zbuffer {
+                zbuffer = tmp.distance;
+                closest = tmp;
+            }
+        }
+
+        closest
+    }
+}
+
+// Copia de una geometría compartida colocada en el mundo con su propia transformación
+pub struct Instance {
+    pub geometry: Arc<dyn RayIntersect>,
+    pub transform: Transform,
+}
+
+impl Instance {
+    pub fn new(geometry: Arc<dyn RayIntersect>, transform: Transform) -> Self {
+        Instance { geometry, transform }
+    }
+}
+
+impl RayIntersect for Instance {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        // Llevamos el rayo al espacio del objeto. La dirección no se normaliza,
+        // así la distancia `t` sigue siendo válida en el espacio del mundo.
+        let local_origin = self.transform.inverse_point(ray_origin);
+        let local_direction = self.transform.inverse_vector(ray_direction);
+
+        let mut intersect = self.geometry.ray_intersect(&local_origin, &local_direction);
+        if !intersect.is_intersecting {
+            return intersect;
+        }
+
+        // Regresamos el punto y la normal al espacio del mundo
+        intersect.point = ray_origin + ray_direction * intersect.distance;
+        intersect.normal = self.transform.transform_normal(&intersect.normal);
+        intersect.uv_density /= self.transform.scale_factor();
+        intersect.tangent = self.transform.transform_vector(&intersect.tangent).normalize();
+        intersect.bitangent = self.transform.transform_vector(&intersect.bitangent).normalize();
+        intersect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use std::f32::consts::PI;
+
+    fn unit_cube() -> Arc<dyn RayIntersect> {
+        Arc::new(Cube::new(Vec3::new(-0.5, -0.5, -0.5), Vec3::new(0.5, 0.5, 0.5), 0))
+    }
+
+    #[test]
+    fn test_instances_share_geometry() {
+        let cube = unit_cube();
+        let a = Instance::new(cube.clone(), Transform::translation(Vec3::new(-3.0, 0.0, 0.0)));
+        let b = Instance::new(cube.clone(), Transform::translation(Vec3::new(3.0, 0.0, 0.0)));
+
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let hit_a = a.ray_intersect(&Vec3::new(-3.0, 5.0, 0.0), &down);
+        let hit_b = b.ray_intersect(&Vec3::new(3.0, 5.0, 0.0), &down);
+        assert!(hit_a.is_intersecting && hit_b.is_intersecting);
+        assert!((hit_a.distance - 4.5).abs() < 1e-5);
+        assert!((hit_b.point - Vec3::new(3.0, 0.5, 0.0)).norm() < 1e-5);
+        assert!(!a.ray_intersect(&Vec3::new(3.0, 5.0, 0.0), &down).is_intersecting);
+        assert_eq!(Arc::strong_count(&cube), 3);
+    }
+
+    #[test]
+    fn test_rotated_and_scaled_instance() {
+        // Cubo estirado al doble en x y girado 45° alrededor de z
+        let transform = Transform::scaling(Vec3::new(2.0, 1.0, 1.0))
+            .then(&Transform::rotation(PI / 4.0, Vec3::new(0.0, 0.0, 1.0)));
+        let instance = Instance::new(unit_cube(), transform);
+
+        let hit = instance.ray_intersect(&Vec3::new(0.0, 5.0, 0.0), &Vec3::new(0.0, -1.0, 0.0));
+        assert!(hit.is_intersecting);
+
+        // La normal resultante es unitaria y apunta hacia el rayo
+        assert!((hit.normal.norm() - 1.0).abs() < 1e-5);
+        assert!(hit.normal.y > 0.0);
+        let expected = transform.transform_normal(&Vec3::new(0.0, 1.0, 0.0));
+        assert!((hit.normal - expected).norm() < 1e-5);
+    }
+}
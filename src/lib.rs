@@ -0,0 +1,34 @@
+// Módulos del trazador de rayos; el programa de main.rs arma la escena y la ventana con ellos
+pub mod framebuffer;
+pub mod ray_intersect;
+pub mod color;
+pub mod sphere;
+pub mod materials;
+pub mod material_library;
+pub mod camera;
+pub mod light;
+pub mod textures;
+pub mod cube;
+pub mod scene;
+pub mod transform;
+pub mod instance;
+pub mod noise;
+pub mod procedural;
+pub mod waves;
+pub mod optics;
+pub mod sampling;
+pub mod pbr;
+pub mod tracing;
+pub mod whitted;
+pub mod path_tracer;
+pub mod accumulator;
+pub mod fog;
+pub mod clouds;
+pub mod ambient;
+pub mod aov;
+pub mod cli;
+pub mod denoise;
+pub mod tiles;
+pub mod resolution;
+pub mod font;
+pub mod hud;
@@ -1,217 +1,513 @@
 use rayon::prelude::*; // Importa Rayon para paralelismo
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use nalgebra_glm::{Vec3, normalize};
-use std::f32::INFINITY;
 use std::f32::consts::PI;
-use std::time::Instant;
-use crate::framebuffer::Framebuffer;
-use crate::ray_intersect::{RayIntersect, Intersect};
-use crate::color::Color;
-use crate::materials::{Material, TextureManager};
-use crate::camera::Camera;
-use crate::light::Light;
-use crate::cube::Cube;
-use crate::scene::Scene;
-
-mod framebuffer;
-mod ray_intersect;
-mod color;
-mod sphere;
-mod materials;
-mod camera;
-mod light;
-mod textures;
-mod cube;
-mod scene;
-
-fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
-    incident - 2.0 * incident.dot(normal) * normal
+use std::time::{Duration, Instant};
+use raytracing::framebuffer::{Framebuffer, SharedFramebuffer};
+use raytracing::ray_intersect::{RayIntersect, RayCone};
+use raytracing::color::Color;
+use raytracing::materials::TextureManager;
+use raytracing::material_library::MaterialLibrary;
+use raytracing::procedural::{ProceduralTexture, Pattern, NoiseKind, TextureSpace};
+use raytracing::tracing::RenderContext;
+use raytracing::whitted::cast_ray;
+use raytracing::path_tracer::trace_path;
+use raytracing::accumulator::{Accumulator, SamplingBudget};
+use raytracing::sphere::Sphere;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use raytracing::camera::Camera;
+use raytracing::light::Light;
+use raytracing::cube::{Cube, UvMapping};
+use raytracing::scene::Scene;
+use raytracing::fog::Fog;
+use raytracing::clouds::CloudLayer;
+use raytracing::ambient::{SkyLight, ambient_occlusion_pass};
+use raytracing::aov::{Aov, RenderPasses};
+use raytracing::cli::{Options, USAGE};
+use raytracing::denoise::{Denoiser, Guides};
+use raytracing::resolution::DynamicResolution;
+use raytracing::hud::{RenderStats, draw_hud};
+use raytracing::tracing::{flush_all_ray_counts, flush_ray_count, rays_traced};
+use raytracing::tiles::{CancelToken, TileOrder, TILE_SIZE, tiles};
+use raytracing::instance::{Group, Instance};
+use raytracing::transform::Transform;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+// Algoritmo usado para calcular el color de cada píxel
+enum Integrator {
+    Whitted,                 // Luz directa más reflejos y refracciones; rápido y sin ruido
+    // Iluminación global con caminos aleatorios, acumulados entre cuadros, y las guías del
+    // filtro de ruido, que no cambian hasta que se reinicia la acumulación
+    PathTraced(Accumulator, Option<Guides>),
+    AmbientOcclusion,        // Pase de depuración: solo la oclusión ambiental, en grises
 }
 
-fn cast_shadow(
-    intersect: &Intersect,
-    light: &Light,
-    objects: &[Cube],
-) -> f32 {
-    let light_dir = (light.position - intersect.point).normalize();
-
-    // Ajusta el origen del rayo de sombra para evitar la autointersección
-    let offset = intersect.normal * 1e-4; // Pequeño valor para evitar estar dentro del cubo
-    let shadow_ray_origin = intersect.point + offset;
-
-    let mut shadow_intensity = 0.0;
+// Muestras por píxel tras las cuales el trazador de caminos deja de refinar la imagen
+const MAX_ACCUMULATED_SAMPLES: u32 = 1024;
+// Muestras que recibe cada píxel antes de fiarse de su varianza
+const MIN_ACCUMULATED_SAMPLES: u32 = 4;
+// Error relativo con el que la ventana deja de refinar un píxel
+const PREVIEW_THRESHOLD: f32 = 0.02;
+
+// Integrador elegido desde la ventana
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderMode {
+    Whitted,
+    PathTraced,
+    AmbientOcclusion,
+}
 
-    for object in objects {
-        let shadow_intersect = object.ray_intersect(&shadow_ray_origin, &light_dir);
-        if shadow_intersect.is_intersecting {
-            shadow_intensity = 0.7;
-            break;
+impl Integrator {
+    fn new(mode: RenderMode, width: usize, height: usize) -> Self {
+        match mode {
+            RenderMode::Whitted => Integrator::Whitted,
+            RenderMode::PathTraced => Integrator::PathTraced(Accumulator::new(width, height,
+                SamplingBudget::adaptive(MIN_ACCUMULATED_SAMPLES, MAX_ACCUMULATED_SAMPLES, PREVIEW_THRESHOLD)), None),
+            RenderMode::AmbientOcclusion => Integrator::AmbientOcclusion,
         }
     }
 
-    shadow_intensity
-}
-
-fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
-    let cosi = -incident.dot(normal).max(-1.0).min(1.0);
-    
-    let (n_cosi, eta, n_normal);
-    
-    if cosi < 0.0 {
-        n_cosi = -cosi;
-        eta = 1.0 / eta_t;
-        n_normal = -normal;
-    } else {
-        // Ray is leaving the object
-        n_cosi = cosi;
-        eta = eta_t;
-        n_normal = *normal;
+    // La imagen acumulada deja de ser válida (cámara o escena cambiaron)
+    fn restart(&mut self) {
+        if let Integrator::PathTraced(accumulator, guides) = self {
+            accumulator.reset();
+            *guides = None;
+        }
     }
-    
-    let k = 1.0 - eta * eta * (1.0 - n_cosi * n_cosi);
-    
-    if k < 0.0 {
-        // Total internal reflection
-        reflect(incident, &n_normal)
-    } else {
-        eta * incident + (eta * n_cosi - k.sqrt( )) * n_normal
+
+    // Indica si todavía vale la pena renderizar aunque nada haya cambiado
+    fn is_converging(&self) -> bool {
+        match self {
+            Integrator::Whitted | Integrator::AmbientOcclusion => false,
+            Integrator::PathTraced(accumulator, _) => !accumulator.is_converged(),
+        }
     }
-}
 
-fn cast_ray(
-    ray_origin: &Vec3, 
-    ray_direction: &Vec3, 
-    objects: &[Cube], 
-    lights: &[Light], 
-    depth: u32,
-    texture_manager: &TextureManager
-) -> Color {
-    
-    if depth > 3 {
-        return Color::new(130, 189, 188); // Color de fondo si excedemos la profundidad máxima
+    // Nombre que se muestra en las estadísticas
+    fn name(&self) -> &'static str {
+        match self {
+            Integrator::Whitted => "Whitted",
+            Integrator::PathTraced(..) => "trazado de caminos",
+            Integrator::AmbientOcclusion => "oclusión ambiental",
+        }
     }
 
-    let mut intersect = Intersect::empty();
-    let mut zbuffer = INFINITY; // El objeto más cercano golpeado por el rayo
-    
-    // Verificamos la intersección del rayo con los cubos
-    for object in objects {
-        let tmp = object.ray_intersect(ray_origin, ray_direction);
-        if tmp.is_intersecting && tmp.distance < zbuffer {
-            zbuffer = tmp.distance;
-            intersect = tmp;
+    // Muestras por píxel acumuladas en promedio
+    fn samples(&self) -> Option<f32> {
+        match self {
+            Integrator::PathTraced(accumulator, _) => Some(accumulator.mean_samples()),
+            Integrator::Whitted | Integrator::AmbientOcclusion => None,
         }
     }
+}
 
-    if !intersect.is_intersecting {
-        return Color::new(130, 189, 188); // Color de fondo
+// Estadísticas de un cuadro que empezó en `started`, cuando el contador de rayos valía `rays_before`
+fn frame_stats(started: Instant, rays_before: u64, integrator: &Integrator, scene: &Scene, camera: &Camera,
+    framebuffer: &Framebuffer) -> RenderStats {
+    RenderStats {
+        frame_time: started.elapsed(),
+        rays: rays_traced() - rays_before,
+        samples: integrator.samples(),
+        time_of_day: scene.normalized_time(),
+        camera: camera.eye,
+        mode: integrator.name(),
+        resolution: (framebuffer.width, framebuffer.height),
     }
+}
 
-    let material = intersect.material;
+// Dirección del rayo primario que pasa por la posición (x, y) de la pantalla, en píxeles
+fn primary_ray(camera: &Camera, x: f32, y: f32, width: f32, height: f32, perspective_scale: f32) -> Vec3 {
+    let aspect_ratio = width / height;
+    let screen_x = (2.0 * x) / width - 1.0;
+    let screen_y = -(2.0 * y) / height + 1.0;
 
-    // Si el material es emisivo, sumamos su emisión
-    let mut final_color = if material.is_emissive() {
-        material.get_emission() // Obtener la emisión del material
-    } else {
-        Color::black()
-    };
+    let screen_x = screen_x * aspect_ratio * perspective_scale;
+    let screen_y = screen_y * perspective_scale;
 
-    let view_dir = (ray_origin - intersect.point).normalize();
+    let ray_direction = normalize(&Vec3::new(screen_x, screen_y, -1.0));
+    camera.basis_change(&ray_direction)
+}
 
-    // Iteramos sobre todas las luces
-    for light in lights {
-        let light_dir = (light.position - intersect.point).normalize();
-        let reflect_dir = reflect(&-light_dir, &intersect.normal);
+// Tiempo mínimo entre dos publicaciones de un cuadro a medias
+const PRESENT_INTERVAL: Duration = Duration::from_millis(33);
 
-        let shadow_intensity = cast_shadow(&intersect, light, objects);
-        let light_intensity = light.intensity * (1.0 - shadow_intensity);
+// Tiempo de cuadro que se busca mientras se mueve la cámara (unos 15 cuadros por segundo)
+const NAVIGATION_FRAME_TIME: Duration = Duration::from_millis(66);
+// Tiempo sin mover la cámara tras el cual se vuelve a la resolución completa
+const NAVIGATION_GRACE: Duration = Duration::from_millis(200);
 
-        // Componente difusa
-        let diffuse_intensity = intersect.normal.dot(&light_dir).max(0.0).min(1.0);
-        let diffuse_color = intersect.material.get_diffuse_color(intersect.u, intersect.v, texture_manager);
-        let diffuse = diffuse_color * intersect.material.albedo[0] * diffuse_intensity * light_intensity;
+// Apertura vertical de la cámara
+const FIELD_OF_VIEW: f32 = PI / 3.0;
 
-        // Componente especular
-        let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(intersect.material.specular);
-        let specular = light.color * intersect.material.albedo[1] * specular_intensity * light_intensity;
+// Luces de la escena: el sol primero (la niebla y las nubes lo buscan ahí) y luego las demás
+fn scene_lights(scene: &Scene, lights: &[Light]) -> Vec<Light> {
+    let mut all_lights = Vec::with_capacity(lights.len() + 1);
+    all_lights.push(scene.light);       // Agregar la luz principal
+    all_lights.extend_from_slice(lights); // Agregar las luces adicionales
+    all_lights
+}
 
-        // Sumar luz difusa y especular de esta luz al color final
-        final_color = final_color + diffuse + specular;
+fn render_context<'a>(objects: &'a [Box<dyn RayIntersect>], all_lights: &'a [Light], texture_manager: &'a TextureManager,
+    materials: &'a MaterialLibrary, scene: &'a Scene) -> RenderContext<'a> {
+    RenderContext {
+        objects,
+        lights: all_lights,
+        texture_manager,
+        materials,
+        time: scene.time_of_day,
+        fog: scene.fog.map(|fog| fog.at_time_of_day(scene.normalized_time())),
+        clouds: scene.clouds.as_ref(),
+        sky_light: scene.sky_light,
     }
+}
 
-    // Cálculo de reflexión
-    let mut reflect_color = Color::black();
-    let reflectivity = intersect.material.reflectivity;
-    let epsilon = 1e-3; // Pequeño desplazamiento para evitar "acné"
-
-    if reflectivity > 0.0 {
-        let reflect_dir = reflect(&-ray_direction, &intersect.normal).normalize();
-        let reflect_origin = intersect.point + intersect.normal * epsilon;
-        reflect_color = cast_ray(&reflect_origin, &reflect_dir, objects, lights, depth + 1, texture_manager);
-    }
+// Cómo se entrega un cuadro mientras se renderiza
+struct Progress<'a> {
+    order: TileOrder,
+    cancel: &'a CancelToken,
+    present: &'a mut dyn FnMut(&Framebuffer), // Se llama cada vez que termina un grupo de bloques
+}
 
-    // Cálculo de refracción
-    let mut refract_color = Color::black();
-    let transparency = intersect.material.transparency;
+// Renderiza la imagen por bloques: cada grupo de bloques se calcula en paralelo y, al
+// terminar, `write` copia sus píxeles al framebuffer y se muestra el avance. `shade` devuelve
+// None para los píxeles que no hay que volver a calcular. Devuelve false si se canceló.
+fn render_tiles<T: Send>(framebuffer: &mut Framebuffer, progress: &mut Progress, shade: impl Fn(usize) -> Option<T> + Sync,
+    mut write: impl FnMut(&mut Framebuffer, usize, T)) -> bool {
+    let tiles = tiles(framebuffer.width, framebuffer.height, TILE_SIZE, progress.order);
+    let cancel = progress.cancel;
+    let width = framebuffer.width;
+
+    for group in tiles.chunks(rayon::current_num_threads() * 2) {
+        let pixels: Vec<Vec<(usize, T)>> = group
+            .par_iter()
+            .map(|tile| {
+                let pixels = tile.pixels(width)
+                    .take_while(|_| !cancel.is_cancelled())
+                    .filter_map(|index| shade(index).map(|value| (index, value)))
+                    .collect();
+                flush_ray_count();
+                pixels
+            })
+            .collect();
+        if cancel.is_cancelled() {
+            return false;
+        }
 
-    if transparency > 0.0 {
-        let refract_dir = refract(&ray_direction, &intersect.normal, intersect.material.refraction_index);
-        let refract_origin = intersect.point - intersect.normal * epsilon;
-        refract_color = cast_ray(&refract_origin, &refract_dir, objects, lights, depth + 1, texture_manager);
+        for (index, value) in pixels.into_iter().flatten() {
+            write(framebuffer, index, value);
+        }
+        (progress.present)(framebuffer);
     }
-
-    // Combinar resultados: color difuso + especular + reflexión + refracción
-    (final_color * (1.0 - reflectivity - transparency)) + (reflect_color * reflectivity) + (refract_color * transparency)
+    true
 }
 
-fn render(framebuffer: &mut Framebuffer, objects: &[Cube], camera: &Camera, 
-    texture_manager: &TextureManager, lights: &[Light], scene: &mut Scene, delta_time: f32) {
+// Renderiza un cuadro. Devuelve false si se canceló antes de terminar; en ese caso el
+// framebuffer queda a medias y el trazador de caminos no acumula nada.
+#[allow(clippy::too_many_arguments)]
+fn render(framebuffer: &mut Framebuffer, objects: &[Box<dyn RayIntersect>], camera: &Camera, 
+    texture_manager: &TextureManager, materials: &MaterialLibrary, lights: &[Light], scene: &mut Scene, delta_time: f32,
+    integrator: &mut Integrator, denoiser: Option<&Denoiser>, progress: &mut Progress) -> bool {
     let width = framebuffer.width as f32;
     let height = framebuffer.height as f32;
-    let aspect_ratio = width / height;
-    let fov = PI / 3.0;
-    let perspective_scale = (fov / 2.0).tan();
+    let image_width = framebuffer.width;
+    let perspective_scale = (FIELD_OF_VIEW / 2.0).tan();
+    let cone = RayCone::from_pixel(FIELD_OF_VIEW, height);
     
     update_lighting(scene,delta_time);
     // Combinar la luz de la escena con las luces adicionales
-    let mut all_lights = Vec::with_capacity(lights.len() + 1);
-    all_lights.push(scene.light);       // Agregar la luz principal
-    all_lights.extend_from_slice(lights); // Agregar las luces adicionales
-    
-    let pixels: Vec<(usize, usize, Color)> = (0..framebuffer.height)
-        .into_par_iter() // Iteramos en paralelo sobre las filas
-        .flat_map(|y| {
-            let all_lights = all_lights.clone();
-            (0..framebuffer.width)
-                .into_par_iter() // Iteramos en paralelo sobre las columnas
-                .map(move |x| {
-                    let screen_x = (2.0 * x as f32) / width - 1.0;
-                    let screen_y = -(2.0 * y as f32) / height + 1.0;
-
-                    let screen_x = screen_x * aspect_ratio * perspective_scale;
-                    let screen_y = screen_y * perspective_scale;
-
-                    let ray_direction = normalize(&Vec3::new(screen_x, screen_y, -1.0));
-                    let rotated_direction = camera.basis_change(&ray_direction);
-                    let pixel_color = cast_ray(&camera.eye, &rotated_direction, objects, &all_lights, 0, &texture_manager);
-
-                    (x, y, pixel_color)
+    let all_lights = scene_lights(scene, lights);
+    let context = render_context(objects, &all_lights, texture_manager, materials, scene);
+    let write_pixel = |framebuffer: &mut Framebuffer, index: usize, color: Color| {
+        framebuffer.set_current_color(color);
+        framebuffer.point((index % image_width) as f32, (index / image_width) as f32);
+    };
+
+    match integrator {
+        Integrator::Whitted | Integrator::AmbientOcclusion => {
+            // El pase de oclusión usa los parámetros de la luz del cielo de la escena
+            let occlusion = matches!(integrator, Integrator::AmbientOcclusion)
+                .then(|| scene.sky_light.unwrap_or(SkyLight::new(1.0)));
+            render_tiles(framebuffer, progress, |index| {
+                let x = (index % image_width) as f32;
+                let y = (index / image_width) as f32;
+                let direction = primary_ray(camera, x, y, width, height, perspective_scale);
+                Some(match &occlusion {
+                    Some(sky_light) => ambient_occlusion_pass(&camera.eye, &direction, sky_light, &context, cone),
+                    None => Color::from_vec3(cast_ray(&camera.eye, &direction, &context, 0, cone, None)),
                 })
-                .collect::<Vec<_>>()
-        })
-        .collect();
+            }, write_pixel)
+        }
+        Integrator::PathTraced(accumulator, guides) => {
+            // Una muestra por cuadro, con una posición aleatoria dentro del píxel, solo para
+            // los píxeles que todavía tienen demasiado ruido
+            let frame_index = accumulator.samples() as u64;
+            let mut frame: Vec<(usize, Vec3)> = Vec::new();
+            let completed = render_tiles(framebuffer, progress, |index| {
+                if !accumulator.needs_samples(index) {
+                    return None;
+                }
+                let mut rng = StdRng::seed_from_u64((frame_index << 32) ^ index as u64);
+                let x = (index % image_width) as f32 + rng.gen::<f32>() - 0.5;
+                let y = (index / image_width) as f32 + rng.gen::<f32>() - 0.5;
+                let direction = primary_ray(camera, x, y, width, height, perspective_scale);
+                Some(trace_path(&camera.eye, &direction, &context, cone, &mut rng))
+            }, |framebuffer, index, sample| {
+                write_pixel(framebuffer, index, Color::from_vec3(accumulator.preview(index, &sample)));
+                frame.push((index, sample));
+            });
+            if !completed {
+                return false;
+            }
+
+            accumulator.add_samples(&frame);
+            match denoiser {
+                // Filtrado guiado por la geometría: la vista previa se ve limpia con pocas muestras
+                Some(denoiser) => {
+                    let guides = guides.get_or_insert_with(|| Guides::render(framebuffer.width, framebuffer.height, &camera.eye, |x, y| {
+                        primary_ray(camera, x, y, width, height, perspective_scale)
+                    }, &context, cone));
+                    let image = denoiser.denoise(&accumulator.image(), guides);
+                    for (index, color) in image.iter().enumerate() {
+                        write_pixel(framebuffer, index, Color::from_vec3(*color));
+                    }
+                }
+                None => accumulator.resolve(framebuffer),
+            }
+            true
+        }
+    }
+}
 
-    for (x, y, color) in pixels {
-        framebuffer.set_current_color(color);
-        framebuffer.point(x as f32, y as f32);
+// Modo sin ventana: renderiza un cuadro en el momento `options.time` del ciclo de día y
+// guarda la imagen final y los pases pedidos. Con `options.samples` mayor que cero, la
+// imagen final sale del trazador de caminos (con muestreo adaptativo si se dio un umbral);
+// los demás pases, del primer impacto, solo sirven de guía al filtro de ruido si se pidió
+// `--denoise` (las opciones no dejan guardarlos junto a esa imagen).
+#[allow(clippy::too_many_arguments)]
+fn render_headless(options: &Options, framebuffer: &mut Framebuffer, objects: &[Box<dyn RayIntersect>], camera: &Camera,
+    texture_manager: &TextureManager, materials: &MaterialLibrary, lights: &[Light], scene: &mut Scene) -> Result<(), String> {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    update_lighting(scene, options.time);
+    let (started, rays_before) = (Instant::now(), rays_traced());
+
+    let mut path_traced = None;
+    let mut stats = None;
+    if options.samples > 0 {
+        let budget = match options.threshold {
+            Some(threshold) => SamplingBudget::adaptive(options.min_samples, options.samples, threshold),
+            None => SamplingBudget::uniform(options.samples),
+        };
+        let mut integrator = Integrator::PathTraced(Accumulator::new(width, height, budget), None);
+        let cancel = CancelToken::default();
+        let mut progress = Progress { order: TileOrder::Spiral, cancel: &cancel, present: &mut |_| {} };
+        while integrator.is_converging() {
+            render(framebuffer, objects, camera, texture_manager, materials, lights, scene, 0.0, &mut integrator, None, &mut progress);
+        }
+        // Se mide antes del pase de AOV para no contar sus rayos como parte del cuadro
+        flush_all_ray_counts();
+        stats = Some(frame_stats(started, rays_before, &integrator, scene, camera, framebuffer));
+        if let Integrator::PathTraced(accumulator, _) = &integrator {
+            println!("{:.1} muestras por píxel en promedio", accumulator.mean_samples());
+            if let Some(heatmap) = &options.heatmap {
+                accumulator.save_heatmap(heatmap).map_err(|e| format!("{}: {}", heatmap.display(), e))?;
+                println!("{}", heatmap.display());
+            }
+        }
+        path_traced = Some(integrator);
+    }
+
+    let all_lights = scene_lights(scene, lights);
+    let context = render_context(objects, &all_lights, texture_manager, materials, scene);
+    let perspective_scale = (FIELD_OF_VIEW / 2.0).tan();
+    let cone = RayCone::from_pixel(FIELD_OF_VIEW, height as f32);
+    let mut passes = RenderPasses::render(width, height, &camera.eye, |x, y| {
+        primary_ray(camera, x, y, width as f32, height as f32, perspective_scale)
+    }, &context, cone);
+
+    // Con el trazador de Whitted la imagen final sale del mismo pase que las AOV
+    let stats = stats.unwrap_or_else(|| frame_stats(started, rays_before, &Integrator::Whitted, scene, camera, framebuffer));
+
+    if let Some(Integrator::PathTraced(accumulator, _)) = &path_traced {
+        for (index, beauty) in passes.layer_mut(Aov::Beauty).iter_mut().enumerate() {
+            *beauty = accumulator.average(index % width, index / width);
+        }
+    }
+
+    if options.denoise {
+        let denoised = Denoiser::default().denoise(passes.layer(Aov::Beauty), &Guides::from_passes(&passes));
+        passes.layer_mut(Aov::Beauty).copy_from_slice(&denoised);
+    }
+    if options.stats {
+        print!("{}", stats);
+    }
+
+    if let Some(output) = &options.output {
+        passes.save_png(Aov::Beauty, output).map_err(|e| format!("{}: {}", output.display(), e))?;
+        println!("{}", output.display());
+        if options.passes {
+            for path in passes.save_pngs(output).map_err(|e| format!("{}: {}", output.display(), e))? {
+                println!("{}", path.display());
+            }
+        }
+    }
+    if let Some(exr) = &options.exr {
+        passes.save_exr(exr).map_err(|e| format!("{}: {}", exr.display(), e))?;
+        println!("{}", exr.display());
+    }
+    Ok(())
+}
+
+// Lo que la ventana controla del render
+#[derive(Debug, Clone)]
+struct ViewSettings {
+    camera: Camera,
+    animating: bool,
+    mode: RenderMode,
+    denoise: bool,
+    fog: bool,
+    clouds: bool,
+    tile_order: TileOrder,
+}
+
+impl ViewSettings {
+    // El trazador de caminos congela el reloj de la animación: si la escena cambiara en cada
+    // cuadro, la acumulación empezaría de cero cada vez y la imagen nunca dejaría de tener ruido
+    fn animates(&self) -> bool {
+        self.animating && self.mode != RenderMode::PathTraced
+    }
+}
+
+// Hilo de render de la ventana: renderiza en su propio framebuffer y publica el avance en
+// `front`, junto con las estadísticas de cada cuadro terminado. Aplica la última
+// configuración recibida antes de cada cuadro y espera sin consumir CPU cuando la imagen
+// ya no cambia. Termina cuando la ventana cierra el canal.
+#[allow(clippy::too_many_arguments)]
+fn render_worker(receiver: Receiver<ViewSettings>, front: &SharedFramebuffer, stats: &Mutex<Option<RenderStats>>, cancel: &CancelToken,
+    mut framebuffer: Framebuffer,
+    objects: &[Box<dyn RayIntersect>], texture_manager: &TextureManager, materials: &MaterialLibrary, lights: &[Light], mut scene: Scene) {
+    let Ok(mut settings) = receiver.recv() else { return };
+    let mut integrator = Integrator::new(settings.mode, framebuffer.width, framebuffer.height);
+    let denoiser = Denoiser::default();
+    let mut hidden_fog = None; // Niebla guardada mientras está apagada
+    let mut hidden_clouds = None;
+    let mut frame_complete = false;
+    let mut restart = true;
+    let mut last_update = Instant::now(); // Para calcular el delta_time
+    let mut last_publish = Instant::now();
+    // Resolución reducida mientras se mueve la cámara
+    let mut resolution = DynamicResolution::new(NAVIGATION_FRAME_TIME);
+    let mut last_camera_move: Option<Instant> = None;
+
+    loop {
+        // Sin nada que refinar se espera a que la ventana cambie algo
+        let idle = !restart && !settings.animates() && frame_complete && !integrator.is_converging();
+        let mut latest = None;
+        if idle {
+            match receiver.recv() {
+                Ok(next) => latest = Some(next),
+                Err(_) => return,
+            }
+        }
+        // Se rearma antes de vaciar el canal: lo que llegue después sí cancela el cuadro que empieza
+        cancel.reset();
+        loop {
+            match receiver.try_recv() {
+                Ok(next) => latest = Some(next),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+
+        if let Some(next) = latest {
+            if next.camera.eye != settings.camera.eye || next.camera.center != settings.camera.center {
+                last_camera_move = Some(Instant::now());
+                restart = true;
+            }
+            if next.animates() && !settings.animates() {
+                last_update = Instant::now();
+            }
+            if next.mode != settings.mode {
+                integrator = Integrator::new(next.mode, framebuffer.width, framebuffer.height);
+                restart = true;
+            }
+            // Mientras la imagen se refina, el cambio de filtro se ve en el cuadro siguiente sin
+            // perder lo acumulado; si ya terminó de refinarse hay que volver a empezar
+            if next.denoise != settings.denoise && !integrator.is_converging() {
+                restart = true;
+            }
+            if next.fog != settings.fog {
+                std::mem::swap(&mut scene.fog, &mut hidden_fog);
+                restart = true;
+            }
+            if next.clouds != settings.clouds {
+                std::mem::swap(&mut scene.clouds, &mut hidden_clouds);
+                restart = true;
+            }
+            settings = next;
+        }
+        // Un cambio que no afecta a la imagen (el orden de los bloques) no hace falta renderizarlo
+        if !restart && !settings.animates() && frame_complete && !integrator.is_converging() {
+            continue;
+        }
+
+        if restart || settings.animates() {
+            integrator.restart();
+        }
+        // Calcular el delta_time
+        let delta_time = if settings.animates() { calculate_delta_time(last_update) } else { 0.0 };
+        last_update = Instant::now();
+
+        // Mientras se navega se renderiza a menor resolución y se estira la imagen; estos cuadros
+        // no se cancelan porque el presupuesto de tiempo ya los mantiene cortos
+        let navigating = last_camera_move.is_some_and(|moved| moved.elapsed() < NAVIGATION_GRACE);
+        if navigating {
+            let (width, height) = resolution.internal_size(framebuffer.width, framebuffer.height);
+            let mut preview = Framebuffer::new(width, height);
+            let mut preview_integrator = Integrator::new(settings.mode, width, height);
+            let never = CancelToken::default();
+            let mut progress = Progress { order: settings.tile_order, cancel: &never, present: &mut |_| {} };
+            let (started, rays_before) = (Instant::now(), rays_traced());
+            render(&mut preview, objects, &settings.camera, texture_manager, materials, lights, &mut scene,
+                delta_time, &mut preview_integrator, settings.denoise.then_some(&denoiser), &mut progress);
+            resolution.update(started.elapsed());
+            *stats.lock().unwrap() = Some(frame_stats(started, rays_before, &preview_integrator, &scene, &settings.camera, &preview));
+
+            framebuffer.upscale_from(&preview);
+            front.publish(&framebuffer);
+            // Al soltar las teclas hace falta un cuadro a resolución completa
+            frame_complete = false;
+            continue;
+        }
+
+        // Publica los bloques a medida que terminan
+        let mut present = |framebuffer: &Framebuffer| {
+            if last_publish.elapsed() >= PRESENT_INTERVAL {
+                front.publish(framebuffer);
+                last_publish = Instant::now();
+            }
+        };
+        let mut progress = Progress { order: settings.tile_order, cancel, present: &mut present };
+        let (started, rays_before) = (Instant::now(), rays_traced());
+        frame_complete = render(&mut framebuffer, objects, &settings.camera, texture_manager, materials, lights, &mut scene,
+            delta_time, &mut integrator, settings.denoise.then_some(&denoiser), &mut progress);
+        if frame_complete {
+            *stats.lock().unwrap() = Some(frame_stats(started, rays_before, &integrator, &scene, &settings.camera, &framebuffer));
+        }
+        front.publish(&framebuffer);
+        last_publish = Instant::now();
+        // Un cuadro cancelado no acumula nada, así que lo acumulado sigue valiendo: el siguiente
+        // cuadro lo continúa en lugar de empezar de cero
+        restart = false;
     }
 }
 
 fn calculate_delta_time(last_update: Instant) -> f32 {
     let now = Instant::now();
     let duration = now.duration_since(last_update);
-    let delta_time = duration.as_secs_f32();
-    delta_time
+    duration.as_secs_f32()
 }
 
 fn update_lighting(scene: &mut Scene, delta_time: f32) {
@@ -219,7 +515,7 @@ fn update_lighting(scene: &mut Scene, delta_time: f32) {
     scene.time_of_day += delta_time;
 
     // Normalizamos el tiempo entre 0 y 1, donde 0 es medianoche y 1 es la próxima medianoche
-    let normalized_time = (scene.time_of_day % scene.cycle_duration) / scene.cycle_duration;
+    let normalized_time = scene.normalized_time();
 
     // Ajustar la posición de la luz para simular el movimiento del sol
     let angle = normalized_time * 2.0 * PI; // Ángulo del ciclo (0 a 2pi)
@@ -252,191 +548,182 @@ fn update_lighting(scene: &mut Scene, delta_time: f32) {
 
 
 fn main() {
-    let mut texture_manager = TextureManager::new();
-    let wood_texture_index = texture_manager.load_texture("assets/wood.png");
-    let leaf_texture_index = texture_manager.load_texture("assets/leaves.png");
-    let grass_texture_index = texture_manager.load_texture("assets/grass.jpg");
-    let stone_texture_index = texture_manager.load_texture("assets/stone.png");
-    let brick_texture_index = texture_manager.load_texture("assets/brick.jpg");
-
-    let soil_material = Material::new_with_texture(
-        grass_texture_index,
-        50.0,
-        [0.6, 0.3],
-        0.6,
-    );
-    let wood_material = Material::new_with_texture(
-        wood_texture_index,
-        50.0,
-        [0.6, 0.3],
-        0.6,
-    );
-    let leaf_material = Material::new_with_texture(
-        leaf_texture_index,
-        50.0,
-        [0.6, 0.3],
-        0.6,
-    );
-    let brick_material = Material::new_with_texture(
-        brick_texture_index,
-        50.0,
-        [0.6, 0.3],
-        0.6,
-    );
-    let stone_material = Material::new_with_texture(
-        stone_texture_index,
-        50.0,
-        [0.6, 0.3],
-        0.6,
-    );
-    let water_material = Material::new(
-        Color::new(115, 136, 255),
-        50.0,
-        [0.6, 0.3],
-        0.8,
-        0.7,
-        0.6
-    );
-    let emissive_material = Material::new_with_emission(
-        Color::new(255, 150, 50),   // Color emisivo (naranja)
-        10.0,                       // Especularidad
-        [0.9, 0.1],                 // Albedo
-        0.0,                        // Reflectividad
-        0.0,                        // Transparencia
-        1.0,                        // Índice de refracción
-        Some(Color::new(255, 150, 50)), // Color de emisión
-        1.0                         // Intensidad de emisión
-    );
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, USAGE);
+        std::process::exit(2);
+    });
 
-    let objects = [
-        Cube {
-            min: Vec3::new(-2.5, -1.0, -1.5),
-            max: Vec3::new(1.5, -0.5, 0.0),
-            material: soil_material,
-        },
-        Cube {
-            min: Vec3::new(-2.5, -1.0, -0.5),
-            max: Vec3::new(-1.0, -0.5, 2.5),
-            material: soil_material,
-        },
-        Cube {
-            min: Vec3::new(1.0, -1.0, -1.0),
-            max: Vec3::new(1.5, 0.0, 2.5),
-            material: soil_material,
-        },
-        Cube {
-            min: Vec3::new(-0.5, -1.0, 0.5),
-            max: Vec3::new(0.5, -0.5, 2.0),
-            material: water_material,
-        },
-        Cube {
-            min: Vec3::new(0.5, -1.0, 0.5),
-            max: Vec3::new(1.0, -0.5, 2.0),
-            material: stone_material,
-        },
-        Cube {
-            min: Vec3::new(-1.0, -1.0, 0.0),
-            max: Vec3::new(-0.5, -0.5, 2.0),
-            material: stone_material,
-        },
-        Cube {
-            min: Vec3::new(-1.0, -1.0, 2.5),
-            max: Vec3::new(1.0, -0.5, 2.0),
-            material: stone_material,
-        },
-        Cube {
-            min: Vec3::new(-0.5, -1.0, 0.5),
-            max: Vec3::new(1.0, -0.5, 0.0),
-            material: stone_material,
-        },
-        Cube {
-            min: Vec3::new(0.0, -0.5, -1.5),
-            max: Vec3::new(1.5, 0.0, 0.0),
-            material: soil_material,
-        },
-        Cube {
-            min: Vec3::new(0.5, 0.0, -1.0),
-            max: Vec3::new(1.0, 1.0, -0.5),
-            material: wood_material,
-        },
-        Cube {
-            min: Vec3::new(0.0, 1.0, -1.5),
-            max: Vec3::new(1.5, 1.5, 0.0),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(0.0, 1.5, -1.0),
-            max: Vec3::new(0.5, 2.0, -0.5),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(0.5, 1.5, -1.5),
-            max: Vec3::new(1.0, 2.0, 0.0),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(1.0, 1.5, -1.0),
-            max: Vec3::new(1.5, 2.0, -0.5),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(0.5, 2.0, -1.0),
-            max: Vec3::new(1.0, 2.5, -0.5),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(-0.85, 0.15, 0.5), //0.15 0.15 0.1
-            max: Vec3::new(-0.7, 0.3,0.65),
-            material: emissive_material,
-        },
-        Cube {
-            min: Vec3::new(-0.85, -0.5, 0.5), //0.15 0.65 0.1
-            max: Vec3::new(-0.7, 0.15, 0.65),
-            material: brick_material,
-        },
-        Cube {
-            min: Vec3::new(0.70, 0.15, 2.0), //0.15 0.15 0.15
-            max: Vec3::new(0.85, 0.3,2.15),
-            material: emissive_material,
-        },
-        Cube {
-            min: Vec3::new(0.70, -0.5, 2.0), //0.15 0.65 0.15
-            max: Vec3::new(0.85, 0.15, 2.15),
-            material: brick_material,
-        },
-        Cube {
-            min: Vec3::new(1.0, 0.0, 1.5),
-            max: Vec3::new(1.5, 1.0, 2.0),
-            material: wood_material,
-        },
-        Cube {
-            min: Vec3::new(0.5, 1.0, 1.0),
-            max: Vec3::new(2.0, 1.5, 2.5),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(0.5, 1.5, 1.5),
-            max: Vec3::new(1.0, 2.0, 2.0),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(1.0, 1.5, 1.0),
-            max: Vec3::new(1.5, 2.0, 2.5),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(1.5, 1.5, 1.5),
-            max: Vec3::new(2.0, 2.0, 2.0),
-            material: leaf_material,
-        },
-        Cube {
-            min: Vec3::new(1.0, 2.0, 1.5),
-            max: Vec3::new(1.5, 2.5, 2.0),
-            material: leaf_material,
-        },
+    // Una textura que falte se verá como un tablero magenta en lugar de detener el programa
+    let mut texture_manager = TextureManager::new().with_fallback();
+    // Texturas procedurales a las que se refiere la paleta de materiales por nombre;
+    // las imágenes se cargan a partir de sus rutas al leer la paleta
+    let procedural_textures = [
+        ("dirt", Pattern::Fbm { low: Color::new(92, 62, 40), high: Color::new(150, 108, 76), scale: 8.0, octaves: 4 },
+            TextureSpace::World, 7),
+        ("gold_wear", Pattern::Fbm { low: Color::new(60, 60, 60), high: Color::new(255, 255, 255), scale: 6.0, octaves: 4 },
+            TextureSpace::World, 11),
+        // Tablero de 4x4 casillas en cada cara, útil para revisar las UV
+        ("checker", Pattern::Checker { even: Color::new(230, 230, 230), odd: Color::new(40, 40, 40), size: 0.25 },
+            TextureSpace::Uv, 0),
+        ("gradient", Pattern::Gradient { start: Color::new(40, 60, 120), end: Color::new(230, 230, 255), direction: Vec3::new(0.0, 1.0, 0.0) },
+            TextureSpace::World, 0),
+        ("perlin", Pattern::Noise { kind: NoiseKind::Perlin, low: Color::black(), high: Color::new(255, 255, 255), scale: 4.0 },
+            TextureSpace::World, 3),
+        ("simplex", Pattern::Noise { kind: NoiseKind::Simplex, low: Color::black(), high: Color::new(255, 255, 255), scale: 4.0 },
+            TextureSpace::World, 5),
+        ("worley", Pattern::Noise { kind: NoiseKind::Worley, low: Color::black(), high: Color::new(255, 255, 255), scale: 4.0 },
+            TextureSpace::World, 13),
+        ("wood_grain", Pattern::Wood { light: Color::new(196, 150, 100), dark: Color::new(120, 80, 45), rings: 12.0, turbulence: 0.4 },
+            TextureSpace::World, 17),
+        ("marble", Pattern::Marble { light: Color::new(235, 235, 230), dark: Color::new(90, 90, 100), scale: 3.0, turbulence: 2.0 },
+            TextureSpace::World, 19),
     ];
+    for (name, pattern, space, seed) in procedural_textures {
+        texture_manager.add_procedural(name, ProceduralTexture::new(pattern, space, seed));
+    }
+
+    let materials = MaterialLibrary::load("assets/materials.txt", &mut texture_manager)
+        .unwrap_or_else(|e| panic!("No se pudo leer la paleta de materiales: {}", e));
+    if let Some(path) = &options.save_materials {
+        if let Err(error) = materials.save(path, &texture_manager) {
+            eprintln!("No se pudo guardar la paleta de materiales en {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+    let material = |name: &str| materials.id(name)
+        .unwrap_or_else(|| panic!("La paleta no define el material {}", name));
+    let soil_material = material("grass");
+    let dirt_material = material("dirt");
+    let wood_material = material("wood");
+    let leaf_material = material("leaves");
+    let brick_material = material("brick");
+    let stone_material = material("stone");
+    let water_material = material("water");
+    let gold_material = material("gold");
+    let emissive_material = material("lamp");
+
+    // Las texturas se repiten una vez por bloque, para que las losas largas no se estiren
+    let block_uv = UvMapping::World { block_size: 0.5 };
+
+    let terrain = [
+        Cube::new_block(
+            Vec3::new(-2.5, -1.0, -1.5),
+            Vec3::new(1.5, -0.5, 0.0),
+            soil_material,
+            dirt_material,
+            dirt_material,
+        ),
+        Cube::new_block(
+            Vec3::new(-2.5, -1.0, -0.5),
+            Vec3::new(-1.0, -0.5, 2.5),
+            soil_material,
+            dirt_material,
+            dirt_material,
+        ),
+        Cube::new_block(
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(1.5, 0.0, 2.5),
+            soil_material,
+            dirt_material,
+            dirt_material,
+        ),
+        Cube::new(
+            Vec3::new(-0.5, -1.0, 0.5),
+            Vec3::new(0.5, -0.5, 2.0),
+            water_material,
+        ),
+        // Fondo del estanque, con un escalón poco profundo en el extremo
+        Cube::new(
+            Vec3::new(-0.5, -1.0, 0.5),
+            Vec3::new(0.5, -0.9, 2.0),
+            stone_material,
+        ),
+        Cube::new(
+            Vec3::new(-0.5, -0.9, 1.5),
+            Vec3::new(0.5, -0.7, 2.0),
+            stone_material,
+        ),
+        Cube::new(
+            Vec3::new(0.5, -1.0, 0.5),
+            Vec3::new(1.0, -0.5, 2.0),
+            stone_material,
+        ),
+        Cube::new(
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(-0.5, -0.5, 2.0),
+            stone_material,
+        ),
+        Cube::new(
+            Vec3::new(-1.0, -1.0, 2.5),
+            Vec3::new(1.0, -0.5, 2.0),
+            stone_material,
+        ),
+        Cube::new(
+            Vec3::new(-0.5, -1.0, 0.5),
+            Vec3::new(1.0, -0.5, 0.0),
+            stone_material,
+        ),
+        Cube::new_block(
+            Vec3::new(0.0, -0.5, -1.5),
+            Vec3::new(1.5, 0.0, 0.0),
+            soil_material,
+            dirt_material,
+            dirt_material,
+        ),
+        Cube::new(
+            Vec3::new(-0.85, -0.5, 0.5), //0.15 0.65 0.1
+            Vec3::new(-0.7, 0.15, 0.65),
+            brick_material,
+        ),
+        Cube::new(
+            Vec3::new(0.70, -0.5, 2.0), //0.15 0.65 0.15
+            Vec3::new(0.85, 0.15, 2.15),
+            brick_material,
+        ),
+    ].map(|cube| cube.with_uv_mapping(block_uv));
+
+    // Modelo de árbol en coordenadas locales (la base del tronco está en el origen)
+    let tree_parts: Vec<Box<dyn RayIntersect>> = [
+        Cube::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.5, 1.0, 0.5), wood_material),
+        Cube::new(Vec3::new(-0.5, 1.0, -0.5), Vec3::new(1.0, 1.5, 1.0), leaf_material),
+        Cube::new(Vec3::new(-0.5, 1.5, 0.0), Vec3::new(0.0, 2.0, 0.5), leaf_material),
+        Cube::new(Vec3::new(0.0, 1.5, -0.5), Vec3::new(0.5, 2.0, 1.0), leaf_material),
+        Cube::new(Vec3::new(0.5, 1.5, 0.0), Vec3::new(1.0, 2.0, 0.5), leaf_material),
+        Cube::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.5, 2.5, 0.5), leaf_material),
+    ]
+    .into_iter()
+    .map(|cube| Box::new(cube.with_uv_mapping(block_uv)) as Box<dyn RayIntersect>)
+    .collect();
+    let tree: Arc<dyn RayIntersect> = Arc::new(Group::new(tree_parts));
+
+    let mut objects: Vec<Box<dyn RayIntersect>> = terrain
+        .into_iter()
+        .map(|cube| Box::new(cube) as Box<dyn RayIntersect>)
+        .collect();
 
-    let mut camera = Camera::new(
+    objects.push(Box::new(Sphere {
+        center: Vec3::new(-1.75, -0.25, 1.6),
+        radius: 0.25,
+        material: gold_material,
+    }));
+
+    // Lámparas sobre los postes: cada cara muestra el patrón completo de la textura
+    for corner in [Vec3::new(-0.85, 0.15, 0.5), Vec3::new(0.70, 0.15, 2.0)] {
+        objects.push(Box::new(Cube::new(corner, corner + Vec3::new(0.15, 0.15, 0.15), emissive_material)));
+    }
+
+    // Ambos árboles comparten la misma geometría; el segundo es más bajo y está girado
+    let tree_transforms = [
+        Transform::translation(Vec3::new(0.5, 0.0, -1.0)),
+        Transform::scaling(Vec3::new(0.8, 0.8, 0.8))
+            .then(&Transform::rotation(PI / 4.0, Vec3::new(0.0, 1.0, 0.0)))
+            .then(&Transform::translation(Vec3::new(1.0, 0.0, 1.5))),
+    ];
+    for transform in tree_transforms {
+        objects.push(Box::new(Instance::new(tree.clone(), transform)));
+    }
+
+    let camera = Camera::new(
         Vec3::new(3.0, 5.0, 5.0),
         Vec3::new(0.0, 0.0, 0.0),
         Vec3::new(0.0, 1.0, 0.0),
@@ -459,10 +746,23 @@ fn main() {
     };
     let lights = vec![light,light1,light2];
 
-    let mut framebuffer = Framebuffer::new(800, 600);
+    let mut framebuffer = Framebuffer::new(options.width, options.height);
     
     let mut scene = Scene::new(10.0);     // Crear la escena
-    let mut last_update = Instant::now(); // Para calcular el delta_time
+    // Niebla pegada al suelo que dibuja haces de luz entre las hojas de los árboles
+    scene.fog = Some(Fog::height(0.12, -1.0, 1.5, Color::new(200, 210, 225)).with_anisotropy(0.6));
+    // Nubes por encima de la cámara que el viento arrastra, con sus sombras sobre el terreno
+    scene.clouds = Some(CloudLayer::new(2024, 9.0, 2.0).with_coverage(0.45).with_wind(Vec3::new(0.4, 0.0, 0.15)));
+    // Luz de ambiente del cielo; sin ella lo que no recibe luz directa queda negro
+    scene.sky_light = Some(SkyLight::new(0.35).with_samples(16).with_max_distance(1.5));
+
+    if options.is_headless() {
+        if let Err(error) = render_headless(&options, &mut framebuffer, &objects, &camera, &texture_manager, &materials, &lights, &mut scene) {
+            eprintln!("No se pudo guardar el render: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     let mut window = Window::new(
         "Raytracing",
@@ -472,43 +772,134 @@ fn main() {
     ).unwrap_or_else(|e| {
         panic!("{}", e);
     });
+    window.set_target_fps(60);
 
     let rotation_speed = PI/50.0;
     let zoom_speed = 0.1;
+    let (width, height) = (framebuffer.width, framebuffer.height);
     framebuffer.clear();
     framebuffer.set_background_color(Color::new(25, 20, 2));
 
+    // El render corre en otro hilo para que la ventana siga atendiendo el teclado: la
+    // ventana le envía la configuración cada vez que cambia y muestra lo último publicado
+    let mut settings = ViewSettings {
+        camera,
+        animating: true,
+        mode: RenderMode::Whitted,
+        denoise: true, // Filtro de ruido para la vista previa del trazador de caminos
+        fog: scene.fog.is_some(),
+        clouds: scene.clouds.is_some(),
+        tile_order: TileOrder::Spiral,
+    };
+    let front = Arc::new(SharedFramebuffer::new(width, height));
+    let stats = Arc::new(Mutex::new(None));
+    let cancel = CancelToken::default();
+    let (sender, receiver) = mpsc::channel();
+    sender.send(settings.clone()).unwrap();
+    let worker = {
+        let front = Arc::clone(&front);
+        let stats = Arc::clone(&stats);
+        let cancel = cancel.clone();
+        thread::spawn(move || render_worker(receiver, &front, &stats, &cancel, framebuffer, &objects, &texture_manager, &materials, &lights, scene))
+    };
+    let mut shown_version = 0;
+    let mut show_hud = true;
+
     while window.is_open() && !window.is_key_down(Key::Escape) {
         //CAMERA ORBIT CONTROLS
         if window.is_key_down(Key :: Left) {
-            camera.orbit(rotation_speed, 0.0);
+            settings.camera.orbit(rotation_speed, 0.0);
         }   
         if window.is_key_down(Key :: Right) {
-            camera.orbit(-rotation_speed, 0.0);
+            settings.camera.orbit(-rotation_speed, 0.0);
         }   
         if window.is_key_down(Key :: Up) {
-            camera.orbit(0.0, -rotation_speed);
+            settings.camera.orbit(0.0, -rotation_speed);
         }
         if window.is_key_down(Key :: Down) {
-            camera.orbit(0.0, rotation_speed);
+            settings.camera.orbit(0.0, rotation_speed);
         }
         // camera zoom controls
         if window.is_key_down(Key::Q) {
-            camera.zoom(zoom_speed);
+            settings.camera.zoom(zoom_speed);
         }
         if window.is_key_down(Key::E) {
-            camera.zoom(-zoom_speed);
+            settings.camera.zoom(-zoom_speed);
+        }
+        let mut changed = settings.camera.is_changed();
+        // La tecla P pausa o reanuda la animación (agua y ciclo de día)
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            settings.animating = !settings.animating;
+            changed = true;
+        }
+        // La tecla T alterna entre el trazador de Whitted y el de caminos
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            settings.mode = match settings.mode {
+                RenderMode::Whitted => RenderMode::PathTraced,
+                RenderMode::PathTraced | RenderMode::AmbientOcclusion => RenderMode::Whitted,
+            };
+            changed = true;
+        }
+        // La tecla N activa o desactiva el filtro de ruido del trazador de caminos
+        if window.is_key_pressed(Key::N, KeyRepeat::No) {
+            settings.denoise = !settings.denoise;
+            changed = true;
+        }
+        // La tecla O muestra el pase de oclusión ambiental (depuración)
+        if window.is_key_pressed(Key::O, KeyRepeat::No) {
+            settings.mode = match settings.mode {
+                RenderMode::AmbientOcclusion => RenderMode::Whitted,
+                _ => RenderMode::AmbientOcclusion,
+            };
+            changed = true;
         }
-        if camera.is_changed() {
-            // Calcular el delta_time
-            let delta_time = calculate_delta_time(last_update);
-            last_update = Instant::now();
-            
-            render(&mut framebuffer, &objects, &camera, &texture_manager, &lights, &mut scene, delta_time);
+        // La tecla F enciende o apaga la niebla
+        if window.is_key_pressed(Key::F, KeyRepeat::No) {
+            settings.fog = !settings.fog;
+            changed = true;
+        }
+        // La tecla C muestra u oculta las nubes
+        if window.is_key_pressed(Key::C, KeyRepeat::No) {
+            settings.clouds = !settings.clouds;
+            changed = true;
+        }
+        // La tecla B cambia el orden de los bloques (espiral desde el centro o curva de Hilbert);
+        // el cuadro en curso termina con el orden anterior
+        let mut reordered = false;
+        if window.is_key_pressed(Key::B, KeyRepeat::No) {
+            settings.tile_order = settings.tile_order.next();
+            reordered = true;
+        }
+        // La tecla H muestra u oculta las estadísticas
+        let mut redraw = false;
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            show_hud = !show_hud;
+            redraw = true;
+        }
+        if changed {
+            // El cuadro en curso ya no sirve: se abandona para empezar el nuevo cuanto antes
+            sender.send(settings.clone()).unwrap();
+            cancel.cancel();
+        } else if reordered {
+            sender.send(settings.clone()).unwrap();
         }
 
-        // Actualiza la ventana con el buffer
-        window.update_with_buffer(&framebuffer.to_u32_buffer(), framebuffer.width, framebuffer.height)
-        .unwrap();
+        // Actualiza la ventana con el último cuadro publicado (o solo lee el teclado)
+        let version = front.version();
+        if version != shown_version || redraw {
+            let mut frame = front.snapshot();
+            if let Some(stats) = stats.lock().unwrap().as_ref().filter(|_| show_hud) {
+                draw_hud(&mut frame, stats);
+            }
+            window.update_with_buffer(&frame.to_u32_buffer(), width, height).unwrap();
+            shown_version = version;
+        } else {
+            window.update();
+        }
     }
-}
\ No newline at end of file
+
+    // Al cerrarse el canal el hilo de render termina
+    drop(sender);
+    cancel.cancel();
+    worker.join().unwrap();
+}
@@ -0,0 +1,403 @@
+use crate::color::Color;
+use crate::materials::{Material, ShadingModel, TextureManager, UvTransform};
+use crate::textures::{FilterMode, WrapMode};
+use crate::waves::Waves;
+use nalgebra_glm::Vec2;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Un material con nombre y, opcionalmente, el material del que hereda
+struct MaterialEntry {
+    name: String,
+    base: Option<usize>,
+    material: Material,
+}
+
+// Materiales de la escena definidos una sola vez por nombre. Los objetos guardan el
+// índice del material, igual que los materiales guardan el índice de sus texturas.
+//
+// Formato de archivo: una sección `[nombre]` por material seguida de líneas
+// `propiedad = valor`. Con `base = otro` se parte de un material anterior y solo hace
+// falta escribir lo que cambia. Las texturas se nombran por su ruta (se cargan si aún no
+// lo están) o por el nombre con que se registró la textura procedural.
+pub struct MaterialLibrary {
+    entries: Vec<MaterialEntry>,
+}
+
+impl Default for MaterialLibrary {
+    fn default() -> Self {
+        MaterialLibrary::new()
+    }
+}
+
+impl MaterialLibrary {
+    pub fn new() -> Self {
+        MaterialLibrary { entries: Vec::new() }
+    }
+
+    // Punto de partida de los materiales que no heredan de otro
+    pub fn default_material() -> Material {
+        Material::new(Color::new(255, 255, 255), 50.0, [0.6, 0.3], 0.0, 0.0, 1.0)
+    }
+
+    // Agrega (o reemplaza) un material y devuelve su índice
+    pub fn add(&mut self, name: &str, material: Material) -> usize {
+        self.insert(name, None, material)
+    }
+
+    // Agrega un material que parte de `base` y aplica `modify` sobre una copia
+    pub fn inherit(&mut self, name: &str, base: usize, modify: impl FnOnce(Material) -> Material) -> usize {
+        let material = modify(self.entries[base].material);
+        self.insert(name, Some(base), material)
+    }
+
+    fn insert(&mut self, name: &str, base: Option<usize>, material: Material) -> usize {
+        let entry = MaterialEntry { name: name.to_string(), base, material };
+        match self.id(name) {
+            Some(id) => {
+                self.entries[id] = entry;
+                id
+            }
+            None => {
+                self.entries.push(entry);
+                self.entries.len() - 1
+            }
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.name == name)
+    }
+
+    pub fn get(&self, id: usize) -> &Material {
+        &self.entries[id].material
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.entries[id].name
+    }
+
+    // Lee una biblioteca desde un archivo
+    pub fn load(path: &str, texture_manager: &mut TextureManager) -> io::Result<Self> {
+        let source = fs::read_to_string(path)?;
+        MaterialLibrary::parse(&source, texture_manager)
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}", path, message)))
+    }
+
+    // Guarda la biblioteca en el formato que lee `load`
+    pub fn save(&self, path: &Path, texture_manager: &TextureManager) -> io::Result<()> {
+        fs::write(path, self.to_text(texture_manager)?)
+    }
+
+    // Interpreta el texto de un archivo de materiales; los errores indican la línea
+    pub fn parse(source: &str, texture_manager: &mut TextureManager) -> Result<Self, String> {
+        let mut library = MaterialLibrary::new();
+        let mut current: Option<usize> = None;
+        let mut has_properties = false; // La sección actual ya tiene alguna propiedad
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("{}: {}", number + 1, message);
+
+            if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                current = Some(library.add(name.trim(), MaterialLibrary::default_material()));
+                has_properties = false;
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| error(format!("se esperaba `propiedad = valor`: {}", line)))?;
+            let (key, value) = (key.trim(), value.trim());
+            let id = current.ok_or_else(|| error("propiedad fuera de una sección [material]".to_string()))?;
+
+            if key == "base" {
+                // Copiar la base pisaría lo que ya se haya escrito en la sección
+                if has_properties {
+                    return Err(error("`base` tiene que ser la primera propiedad del material".to_string()));
+                }
+                let base_name = unquote(value);
+                let base = library.id(base_name)
+                    .filter(|&base| base != id)
+                    .ok_or_else(|| error(format!("material base desconocido: {}", base_name)))?;
+                library.entries[id].base = Some(base);
+                library.entries[id].material = library.entries[base].material;
+            } else {
+                set_property(&mut library.entries[id].material, key, value, texture_manager).map_err(error)?;
+            }
+            has_properties = true;
+        }
+
+        Ok(library)
+    }
+
+    // Texto del archivo: cada material solo escribe las propiedades que difieren de su base
+    pub fn to_text(&self, texture_manager: &TextureManager) -> io::Result<String> {
+        let mut text = String::new();
+        for entry in &self.entries {
+            let base = entry.base.map_or_else(MaterialLibrary::default_material, |base| self.entries[base].material);
+            let inherited = properties(&base, texture_manager);
+            let own = properties(&entry.material, texture_manager);
+
+            text.push_str(&format!("[{}]\n", entry.name));
+            if let Some(base) = entry.base {
+                text.push_str(&format!("base = {}\n", self.entries[base].name));
+            }
+            for ((key, value), (_, base_value)) in own.iter().zip(&inherited) {
+                let value = value.as_ref().map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message.clone()))?;
+                if Some(value) != base_value.as_ref().ok() {
+                    text.push_str(&format!("{} = {}\n", key, value));
+                }
+            }
+            text.push('\n');
+        }
+        Ok(text)
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim_matches('"')
+}
+
+fn parse_floats<const N: usize>(value: &str) -> Result<[f32; N], String> {
+    let numbers: Vec<f32> = value
+        .split_whitespace()
+        .map(|part| part.parse::<f32>().map_err(|_| format!("número inválido: {}", part)))
+        .collect::<Result<_, _>>()?;
+    numbers.try_into().map_err(|_| format!("se esperaban {} números: {}", N, value))
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    let [red, green, blue] = parse_floats::<3>(value)?;
+    Ok(Color::new(red as i32, green as i32, blue as i32))
+}
+
+// `none`, `#índice`, o la ruta/nombre de la textura (se carga si es una ruta nueva)
+fn parse_texture(value: &str, texture_manager: &mut TextureManager) -> Result<Option<usize>, String> {
+    if value == "none" {
+        return Ok(None);
+    }
+    if let Some(index) = value.strip_prefix('#') {
+        let index: usize = index.parse().map_err(|_| format!("índice de textura inválido: {}", value))?;
+        if index >= texture_manager.count() {
+            return Err(format!("no existe la textura {}", value));
+        }
+        return Ok(Some(index));
+    }
+    let name = unquote(value);
+    match texture_manager.find_texture(name) {
+        Some(index) => Ok(Some(index)),
+        None => texture_manager.load_texture(name).map(Some).map_err(|error| error.to_string()),
+    }
+}
+
+fn set_property(material: &mut Material, key: &str, value: &str, texture_manager: &mut TextureManager) -> Result<(), String> {
+    let number = || value.parse::<f32>().map_err(|_| format!("número inválido: {}", value));
+    match key {
+        "shading" => {
+            material.shading = match value {
+                "phong" => ShadingModel::Phong,
+                "metallic_roughness" => ShadingModel::MetallicRoughness,
+                _ => return Err(format!("modelo de iluminación desconocido: {}", value)),
+            }
+        }
+        "diffuse" => material.diffuse = parse_color(value)?,
+        "specular" => material.specular = number()?,
+        "albedo" => material.albedo = parse_floats::<2>(value)?,
+        "reflectivity" => material.reflectivity = number()?,
+        "transparency" => material.transparency = number()?,
+        "refraction_index" => material.refraction_index = number()?,
+        "metallic" => material.metallic = number()?,
+        "roughness" => material.roughness = number()?,
+        "texture" => material.texture_index = parse_texture(value, texture_manager)?,
+        "normal_map" => material.normal_map = parse_texture(value, texture_manager)?,
+        "bump_map" => material.bump_map = parse_texture(value, texture_manager)?,
+        "bump_strength" => material.bump_strength = number()?,
+        "metallic_map" => material.metallic_map = parse_texture(value, texture_manager)?,
+        "roughness_map" => material.roughness_map = parse_texture(value, texture_manager)?,
+        "emission" => material.emissive_color = if value == "none" { None } else { Some(parse_color(value)?) },
+        "emission_intensity" => material.emissive_intensity = number()?,
+        "emission_map" => material.emission_map = parse_texture(value, texture_manager)?,
+        "filter" => {
+            material.filter = match value {
+                "nearest" => FilterMode::Nearest,
+                "bilinear" => FilterMode::Bilinear,
+                "trilinear" => FilterMode::Trilinear,
+                _ => return Err(format!("filtro desconocido: {}", value)),
+            }
+        }
+        "wrap" => {
+            material.wrap = match value {
+                "repeat" => WrapMode::Repeat,
+                "clamp" => WrapMode::Clamp,
+                "mirror" => WrapMode::Mirror,
+                _ => return Err(format!("modo de envoltura desconocido: {}", value)),
+            }
+        }
+        "uv_scale" => material.uv_transform.scale = Vec2::from(parse_floats::<2>(value)?),
+        "uv_offset" => material.uv_transform.offset = Vec2::from(parse_floats::<2>(value)?),
+        "uv_rotation" => material.uv_transform.rotation = number()?,
+        "uv_scroll" => material.uv_scroll = Vec2::from(parse_floats::<2>(value)?),
+        "alpha_cutoff" => material.alpha_cutoff = if value == "none" { None } else { Some(number()?) },
+        "waves" => {
+            material.waves = if value == "none" {
+                None
+            } else {
+                let [amplitude, wavelength, speed] = parse_floats::<3>(value)?;
+                Some(Waves::new(amplitude, wavelength, speed))
+            }
+        }
+        "absorption" => material.absorption_color = parse_color(value)?,
+        "absorption_density" => material.absorption_density = number()?,
+        _ => return Err(format!("propiedad desconocida: {}", key)),
+    }
+    Ok(())
+}
+
+fn format_color(color: Color) -> String {
+    format!("{} {} {}", color.red, color.green, color.blue)
+}
+
+fn format_pair(pair: Vec2) -> String {
+    format!("{} {}", pair.x, pair.y)
+}
+
+// Las texturas sin nombre (creadas en código) solo pueden guardarse por índice
+fn format_texture(texture: Option<usize>, texture_manager: &TextureManager) -> Result<String, String> {
+    Ok(match texture {
+        None => "none".to_string(),
+        Some(index) => match texture_manager.texture_name(index) {
+            Some(name) => format!("\"{}\"", name),
+            None => format!("#{}", index),
+        },
+    })
+}
+
+// Todas las propiedades del material en el formato del archivo, siempre en el mismo orden
+fn properties(material: &Material, texture_manager: &TextureManager) -> Vec<(&'static str, Result<String, String>)> {
+    let texture = |index| format_texture(index, texture_manager);
+    let UvTransform { scale, offset, rotation } = material.uv_transform;
+    vec![
+        ("shading", Ok(match material.shading {
+            ShadingModel::Phong => "phong",
+            ShadingModel::MetallicRoughness => "metallic_roughness",
+        }.to_string())),
+        ("diffuse", Ok(format_color(material.diffuse))),
+        ("specular", Ok(material.specular.to_string())),
+        ("albedo", Ok(format!("{} {}", material.albedo[0], material.albedo[1]))),
+        ("reflectivity", Ok(material.reflectivity.to_string())),
+        ("transparency", Ok(material.transparency.to_string())),
+        ("refraction_index", Ok(material.refraction_index.to_string())),
+        ("metallic", Ok(material.metallic.to_string())),
+        ("roughness", Ok(material.roughness.to_string())),
+        ("texture", texture(material.texture_index)),
+        ("normal_map", texture(material.normal_map)),
+        ("bump_map", texture(material.bump_map)),
+        ("bump_strength", Ok(material.bump_strength.to_string())),
+        ("metallic_map", texture(material.metallic_map)),
+        ("roughness_map", texture(material.roughness_map)),
+        ("emission", Ok(material.emissive_color.map_or("none".to_string(), format_color))),
+        ("emission_intensity", Ok(material.emissive_intensity.to_string())),
+        ("emission_map", texture(material.emission_map)),
+        ("filter", Ok(match material.filter {
+            FilterMode::Nearest => "nearest",
+            FilterMode::Bilinear => "bilinear",
+            FilterMode::Trilinear => "trilinear",
+        }.to_string())),
+        ("wrap", Ok(match material.wrap {
+            WrapMode::Repeat => "repeat",
+            WrapMode::Clamp => "clamp",
+            WrapMode::Mirror => "mirror",
+        }.to_string())),
+        ("uv_scale", Ok(format_pair(scale))),
+        ("uv_offset", Ok(format_pair(offset))),
+        ("uv_rotation", Ok(rotation.to_string())),
+        ("uv_scroll", Ok(format_pair(material.uv_scroll))),
+        ("alpha_cutoff", Ok(material.alpha_cutoff.map_or("none".to_string(), |cutoff| cutoff.to_string()))),
+        ("waves", Ok(material.waves.map_or("none".to_string(), |waves| {
+            format!("{} {} {}", waves.amplitude, waves.wavelength, waves.speed)
+        }))),
+        ("absorption", Ok(format_color(material.absorption_color))),
+        ("absorption_density", Ok(material.absorption_density.to_string())),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALETTE: &str = "
+# Bloques
+[block]
+specular = 50
+albedo = 0.6 0.3
+refraction_index = 0.6
+
+[glass]
+base = block
+transparency = 0.9
+refraction_index = 1.5
+waves = 0.01 0.5 0.2
+
+[tinted]
+base = glass
+absorption = 60 140 190
+absorption_density = 2.5
+";
+
+    #[test]
+    fn test_inheritance_keeps_base_properties() {
+        let mut texture_manager = TextureManager::new();
+        let library = MaterialLibrary::parse(PALETTE, &mut texture_manager).unwrap();
+
+        let tinted = library.get(library.id("tinted").unwrap());
+        assert_eq!(tinted.specular, 50.0);
+        assert_eq!(tinted.transparency, 0.9);
+        assert_eq!(tinted.refraction_index, 1.5);
+        assert_eq!(tinted.absorption_color.green, 140);
+        assert!(tinted.waves.is_some());
+        assert_eq!(library.get(library.id("block").unwrap()).transparency, 0.0);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let mut texture_manager = TextureManager::new();
+        let library = MaterialLibrary::parse(PALETTE, &mut texture_manager).unwrap();
+        let text = library.to_text(&texture_manager).unwrap();
+
+        // Solo se escriben las diferencias con la base
+        assert!(text.contains("[tinted]\nbase = glass\nabsorption = 60 140 190\nabsorption_density = 2.5\n"));
+
+        let reloaded = MaterialLibrary::parse(&text, &mut texture_manager).unwrap();
+        assert_eq!(reloaded.to_text(&texture_manager).unwrap(), text);
+        assert_eq!(reloaded.name(2), "tinted");
+    }
+
+    #[test]
+    fn test_errors_report_the_line() {
+        let mut texture_manager = TextureManager::new();
+        let error = MaterialLibrary::parse("[a]\nspecular = 5\nshininess = 3\n", &mut texture_manager).err().unwrap();
+        assert!(error.starts_with("3: "), "{}", error);
+
+        let error = MaterialLibrary::parse("[a]\nbase = missing\n", &mut texture_manager).err().unwrap();
+        assert!(error.contains("missing"));
+
+        // `base` después de otra propiedad la borraría
+        let error = MaterialLibrary::parse("[a]\n[b]\nspecular = 5\nbase = a\n", &mut texture_manager).err().unwrap();
+        assert!(error.starts_with("4: "), "{}", error);
+    }
+
+    #[test]
+    fn test_code_defined_materials_inherit() {
+        let texture_manager = TextureManager::new();
+        let mut library = MaterialLibrary::new();
+        let block = library.add("block", MaterialLibrary::default_material());
+        let mirror = library.inherit("mirror", block, |material| Material { reflectivity: 0.8, ..material });
+
+        assert_eq!(library.get(mirror).reflectivity, 0.8);
+        let text = library.to_text(&texture_manager).unwrap();
+        assert!(text.ends_with("[mirror]\nbase = block\nreflectivity = 0.8\n\n"));
+    }
+}
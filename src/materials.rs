@@ -1,32 +1,191 @@
 use crate::color::Color;
-use crate::textures::Texture;
+use crate::textures::{Texture, TextureError, FilterMode, WrapMode};
+use crate::procedural::ProceduralTexture;
+use crate::waves::Waves;
+use crate::ray_intersect::Intersect;
+use crate::pbr::{PbrSurface, dielectric_f0, phong_roughness};
+use nalgebra_glm::{Vec2, Vec3};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+// Origen de una textura: una imagen cargada o un patrón calculado
+#[derive(Debug)]
+pub enum TextureSource {
+    Image(Texture),
+    Procedural(ProceduralTexture),
+}
+
+impl TextureSource {
+    // Color en (u, v); los patrones en espacio del mundo usan `point` en su lugar
+    pub fn sample(&self, u: f32, v: f32, point: &Vec3, footprint: f32, filter: FilterMode, wrap: WrapMode) -> Color {
+        match self {
+            TextureSource::Image(texture) => texture.sample(u, v, footprint, filter, wrap),
+            TextureSource::Procedural(procedural) => procedural.evaluate(u, v, point),
+        }
+    }
+
+    // Opacidad en (u, v); los patrones procedurales siempre son opacos
+    pub fn sample_alpha(&self, u: f32, v: f32, footprint: f32, filter: FilterMode, wrap: WrapMode) -> f32 {
+        match self {
+            TextureSource::Image(texture) => texture.sample_alpha(u, v, footprint, filter, wrap),
+            TextureSource::Procedural(_) => 1.0,
+        }
+    }
+
+    // Tamaño del lado mayor en texeles; los patrones procedurales no tienen resolución fija
+    pub fn resolution(&self) -> Option<usize> {
+        match self {
+            TextureSource::Image(texture) => Some(texture.width.max(texture.height)),
+            TextureSource::Procedural(_) => None,
+        }
+    }
+}
+
 // Estructura que contiene las texturas
 pub struct TextureManager {
-    textures: Vec<Arc<Texture>>, // Contenedor de todas las texturas
+    textures: Vec<Arc<TextureSource>>, // Contenedor de todas las texturas
+    names: Vec<Option<String>>,        // Ruta o nombre de cada textura, para referirse a ella desde archivos
+    loaded: HashMap<PathBuf, usize>,   // Índice de cada imagen por su ruta canónica, para decodificarla una sola vez
+    fallback: bool,                    // Reemplazar las imágenes que no cargan por un tablero en vez de fallar
+}
+
+impl Default for TextureManager {
+    fn default() -> Self {
+        TextureManager::new()
+    }
 }
 
 impl TextureManager {
     pub fn new() -> Self {
         TextureManager {
             textures: Vec::new(),
+            names: Vec::new(),
+            loaded: HashMap::new(),
+            fallback: false,
         }
     }
 
-    // Añadir una textura al contenedor y devolver el índice
-    pub fn load_texture(&mut self, path: &str) -> usize {
-        let texture = Arc::new(Texture::new(path));
-        self.textures.push(texture);
+    // Las imágenes que no se puedan cargar se reemplazan por un tablero magenta y negro
+    // (el error se informa por la salida de errores), para que se vean sin detener el programa
+    pub fn with_fallback(mut self) -> Self {
+        self.fallback = true;
+        self
+    }
+
+    // Carga una imagen y devuelve su índice. Si ya se cargó (aunque se escriba la ruta
+    // de otra forma, como `./assets/a.png`), se reutiliza la misma textura.
+    pub fn load_texture(&mut self, path: &str) -> Result<usize, TextureError> {
+        let canonical = fs::canonicalize(path);
+        let key = canonical.as_ref().map_or_else(|_| PathBuf::from(path), |canonical| canonical.clone());
+        if let Some(&index) = self.loaded.get(&key) {
+            return Ok(index);
+        }
+
+        let texture = match canonical {
+            Ok(_) => Texture::load(path),
+            Err(source) => Err(TextureError::Open { path: path.to_string(), source }),
+        };
+        let texture = match texture {
+            Ok(texture) => texture,
+            Err(error) if self.fallback => {
+                eprintln!("Advertencia: {}; se usará un tablero en su lugar", error);
+                Texture::checkerboard()
+            }
+            Err(error) => return Err(error),
+        };
+
+        let index = self.add_texture(texture);
+        self.names[index] = Some(path.to_string());
+        self.loaded.insert(key, index);
+        Ok(index)
+    }
+
+    // Añadir una textura ya construida y devolver el índice
+    pub fn add_texture(&mut self, texture: Texture) -> usize {
+        self.add_source(TextureSource::Image(texture))
+    }
+
+    // Añadir una textura procedural; se usa con el mismo índice que una imagen y los
+    // archivos de materiales la referencian por `name`
+    pub fn add_procedural(&mut self, name: &str, texture: ProceduralTexture) -> usize {
+        let index = self.add_source(TextureSource::Procedural(texture));
+        self.names[index] = Some(name.to_string());
+        index
+    }
+
+    fn add_source(&mut self, source: TextureSource) -> usize {
+        self.textures.push(Arc::new(source));
+        self.names.push(None);
         self.textures.len() - 1 // Devuelve el índice de la textura
     }
 
+    // Cantidad de texturas registradas
+    pub fn count(&self) -> usize {
+        self.textures.len()
+    }
+
+    // Ruta de la imagen o nombre de la textura procedural
+    pub fn texture_name(&self, index: usize) -> Option<&str> {
+        self.names.get(index)?.as_deref()
+    }
+
+    // Índice de la textura con esa ruta o nombre
+    pub fn find_texture(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|candidate| candidate.as_deref() == Some(name))
+    }
+
     // Obtener una referencia a la textura según el índice
-    pub fn get_texture(&self, index: usize) -> &Arc<Texture> {
+    pub fn get_texture(&self, index: usize) -> &Arc<TextureSource> {
         &self.textures[index]
     }
 }
 
+// Transformación de las coordenadas UV antes de leer la textura
+#[derive(Debug, Clone, Copy)]
+pub struct UvTransform {
+    pub scale: Vec2,   // Repeticiones de la textura por unidad UV
+    pub offset: Vec2,  // Desplazamiento aplicado después de escalar y rotar
+    pub rotation: f32, // Rotación en radianes alrededor del origen UV
+}
+
+impl UvTransform {
+    pub fn identity() -> Self {
+        UvTransform {
+            scale: Vec2::new(1.0, 1.0),
+            offset: Vec2::new(0.0, 0.0),
+            rotation: 0.0,
+        }
+    }
+
+    pub fn new(scale: Vec2, offset: Vec2, rotation: f32) -> Self {
+        UvTransform { scale, offset, rotation }
+    }
+
+    pub fn apply(&self, u: f32, v: f32) -> (f32, f32) {
+        let su = u * self.scale.x;
+        let sv = v * self.scale.y;
+        let (sin, cos) = self.rotation.sin_cos();
+        (
+            su * cos - sv * sin + self.offset.x,
+            su * sin + sv * cos + self.offset.y,
+        )
+    }
+
+    // Factor por el que crece la huella del rayo en el espacio de la textura
+    pub fn footprint_scale(&self) -> f32 {
+        self.scale.x.abs().max(self.scale.y.abs())
+    }
+}
+
+// Modelo de iluminación del material
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadingModel {
+    Phong,             // Exponente especular y pesos `albedo` (materiales clásicos)
+    MetallicRoughness, // BRDF de microfacetas GGX con color base, metálico y rugosidad
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Material {
     pub diffuse: Color,
@@ -38,8 +197,27 @@ pub struct Material {
     pub texture_index: Option<usize>, // Índice de la textura en TextureManager
     pub emissive_color: Option<Color>, // Agregar color emisivo
     pub emissive_intensity: f32,
+    pub emission_map: Option<usize>, // Patrón de la emisión; se multiplica por `emissive_color`
+    pub filter: FilterMode, // Filtrado usado al leer la textura
+    pub wrap: WrapMode,     // Qué hacer con las UV fuera de [0, 1]
+    pub uv_transform: UvTransform,
+    pub alpha_cutoff: Option<f32>, // Los texeles con alfa menor a este valor no existen
+    pub normal_map: Option<usize>,  // Mapa de normales en espacio tangente
+    pub bump_map: Option<usize>,    // Mapa de alturas (escala de grises)
+    pub bump_strength: f32,         // Altura en unidades del mundo que representa el blanco
+    pub uv_scroll: Vec2,            // Desplazamiento de las UV por segundo (agua, lava...)
+    pub waves: Option<Waves>,       // Oleaje animado que inclina la normal
+    pub absorption_color: Color,    // Color que queda tras recorrer 1/densidad unidades dentro del objeto
+    pub absorption_density: f32,    // 0.0 = el volumen no absorbe luz
+    pub shading: ShadingModel,
+    pub metallic: f32,              // 0 = dieléctrico, 1 = metal (solo MetallicRoughness)
+    pub roughness: f32,             // 0 = pulido, 1 = mate (solo MetallicRoughness)
+    pub metallic_map: Option<usize>,  // Escala de grises que multiplica a `metallic`
+    pub roughness_map: Option<usize>, // Escala de grises que multiplica a `roughness`
 }
 
+// Los constructores y builders sirven para definir materiales en código; la escena
+// principal los lee de un archivo con MaterialLibrary
 impl Material {
     // Constructor para materiales sin textura
     pub fn new(
@@ -60,6 +238,23 @@ impl Material {
             texture_index: None, 
             emissive_color: None,
             emissive_intensity: 0.0,
+            emission_map: None,
+            filter: FilterMode::Trilinear,
+            wrap: WrapMode::Repeat,
+            uv_transform: UvTransform::identity(),
+            alpha_cutoff: None,
+            normal_map: None,
+            bump_map: None,
+            bump_strength: 0.0,
+            uv_scroll: Vec2::new(0.0, 0.0),
+            waves: None,
+            absorption_color: Color::new(255, 255, 255),
+            absorption_density: 0.0,
+            shading: ShadingModel::Phong,
+            metallic: 0.0,
+            roughness: 1.0,
+            metallic_map: None,
+            roughness_map: None,
         }
     }
 
@@ -80,10 +275,28 @@ impl Material {
             texture_index: Some(texture_index), // Asignar el índice de la textura
             emissive_color: None,
             emissive_intensity: 0.0,
+            emission_map: None,
+            filter: FilterMode::Trilinear,
+            wrap: WrapMode::Repeat,
+            uv_transform: UvTransform::identity(),
+            alpha_cutoff: None,
+            normal_map: None,
+            bump_map: None,
+            bump_strength: 0.0,
+            uv_scroll: Vec2::new(0.0, 0.0),
+            waves: None,
+            absorption_color: Color::new(255, 255, 255),
+            absorption_density: 0.0,
+            shading: ShadingModel::Phong,
+            metallic: 0.0,
+            roughness: 1.0,
+            metallic_map: None,
+            roughness_map: None,
         }
     }
 
     // Constructor con color emisivo
+    #[allow(clippy::too_many_arguments)]
     pub fn new_with_emission(
         diffuse: Color,
         specular: f32,
@@ -104,6 +317,196 @@ impl Material {
             texture_index: None, 
             emissive_color,
             emissive_intensity,
+            emission_map: None,
+            filter: FilterMode::Trilinear,
+            wrap: WrapMode::Repeat,
+            uv_transform: UvTransform::identity(),
+            alpha_cutoff: None,
+            normal_map: None,
+            bump_map: None,
+            bump_strength: 0.0,
+            uv_scroll: Vec2::new(0.0, 0.0),
+            waves: None,
+            absorption_color: Color::new(255, 255, 255),
+            absorption_density: 0.0,
+            shading: ShadingModel::Phong,
+            metallic: 0.0,
+            roughness: 1.0,
+            metallic_map: None,
+            roughness_map: None,
+        }
+    }
+
+    // Material físico: el índice de refracción define la reflectancia de la parte
+    // dieléctrica (1.5 equivale al 4% típico de plásticos y piedras)
+    pub fn new_pbr(base_color: Color, metallic: f32, roughness: f32, refraction_index: f32) -> Self {
+        Material::new(base_color, 0.0, [1.0, 0.0], 0.0, 0.0, refraction_index)
+            .with_pbr(metallic, roughness)
+    }
+
+    // Cambia el material al modelo metálico/rugoso conservando su color y texturas
+    pub fn with_pbr(mut self, metallic: f32, roughness: f32) -> Self {
+        self.shading = ShadingModel::MetallicRoughness;
+        self.metallic = metallic.clamp(0.0, 1.0);
+        self.roughness = roughness.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_metallic_map(mut self, texture_index: usize) -> Self {
+        self.metallic_map = Some(texture_index);
+        self
+    }
+
+    pub fn with_roughness_map(mut self, texture_index: usize) -> Self {
+        self.roughness_map = Some(texture_index);
+        self
+    }
+
+    pub fn with_texture(mut self, texture_index: usize) -> Self {
+        self.texture_index = Some(texture_index);
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_uv_transform(mut self, uv_transform: UvTransform) -> Self {
+        self.uv_transform = uv_transform;
+        self
+    }
+
+    // Recorta los texeles cuyo alfa sea menor que `cutoff` (hojas, rejas, etc.)
+    pub fn with_alpha_cutout(mut self, cutoff: f32) -> Self {
+        self.alpha_cutoff = Some(cutoff);
+        self
+    }
+
+    pub fn with_normal_map(mut self, texture_index: usize) -> Self {
+        self.normal_map = Some(texture_index);
+        self
+    }
+
+    // Emisión con un patrón (lámparas, lava, letreros); se activa con intensidad 1 si no la tenía
+    pub fn with_emission_map(mut self, texture_index: usize) -> Self {
+        self.emission_map = Some(texture_index);
+        if self.emissive_intensity <= 0.0 {
+            self.emissive_intensity = 1.0;
+        }
+        self
+    }
+
+    // `strength` es la altura, en unidades del mundo, de los texeles blancos
+    pub fn with_bump_map(mut self, texture_index: usize, strength: f32) -> Self {
+        self.bump_map = Some(texture_index);
+        self.bump_strength = strength;
+        self
+    }
+
+    // Las UV avanzan `scroll` unidades por segundo de la escena
+    pub fn with_uv_scroll(mut self, scroll: Vec2) -> Self {
+        self.uv_scroll = scroll;
+        self
+    }
+
+    pub fn with_waves(mut self, waves: Waves) -> Self {
+        self.waves = Some(waves);
+        self
+    }
+
+    // Absorción de Beer–Lambert en el interior de los materiales transparentes
+    pub fn with_absorption(mut self, color: Color, density: f32) -> Self {
+        self.absorption_color = color;
+        self.absorption_density = density;
+        self
+    }
+
+    // Fracción de luz por canal que sobrevive tras recorrer `distance` dentro del objeto
+    pub fn transmittance(&self, distance: f32) -> [f32; 3] {
+        if self.absorption_density <= 0.0 {
+            return [1.0; 3];
+        }
+        let channel = |value: u8| {
+            // Coeficiente de absorción: -ln(color) por unidad de densidad
+            let sigma = -(value.max(1) as f32 / 255.0).ln() * self.absorption_density;
+            (-sigma * distance).exp()
+        };
+        [
+            channel(self.absorption_color.red),
+            channel(self.absorption_color.green),
+            channel(self.absorption_color.blue),
+        ]
+    }
+
+    // Coordenadas de textura finales: transformación del material más el desplazamiento animado
+    fn texture_uv(&self, u: f32, v: f32, time: f32) -> (f32, f32) {
+        let (u, v) = self.uv_transform.apply(u, v);
+        (u + self.uv_scroll.x * time, v + self.uv_scroll.y * time)
+    }
+
+    // Lee una textura del material aplicando su transformación UV, filtrado y envoltura
+    fn sample_texture(&self, texture_index: usize, (u, v): (f32, f32), point: &Vec3, footprint: f32, time: f32, texture_manager: &TextureManager) -> Color {
+        let texture = texture_manager.get_texture(texture_index);
+        let (u, v) = self.texture_uv(u, v, time);
+        let footprint = footprint * self.uv_transform.footprint_scale();
+        texture.sample(u, v, point, footprint, self.filter, self.wrap)
+    }
+
+    // Normal usada para sombrear: la geométrica perturbada por el mapa de normales y/o de alturas
+    pub fn shading_normal(&self, intersect: &Intersect, footprint: f32, time: f32, texture_manager: &TextureManager) -> Vec3 {
+        let mut normal = intersect.normal;
+        if let Some(waves) = self.waves {
+            normal = waves.perturb_normal(&normal, &intersect.point, time);
+        }
+
+        let tangent = intersect.tangent;
+        let bitangent = intersect.bitangent;
+        if tangent == Vec3::zeros() {
+            return normal; // La primitiva no proporciona un marco tangente
+        }
+
+        if let Some(normal_map) = self.normal_map {
+            // Del rango [0, 255] al rango [-1, 1] en el espacio tangente
+            let texel = self.sample_texture(normal_map, (intersect.u, intersect.v), &intersect.point, footprint, time, texture_manager);
+            let x = texel.red as f32 / 127.5 - 1.0;
+            let y = texel.green as f32 / 127.5 - 1.0;
+            let z = texel.blue as f32 / 127.5 - 1.0;
+            normal = (tangent * x + bitangent * y + normal * z).normalize();
+        }
+
+        if let Some(bump_map) = self.bump_map {
+            // Diferencias centrales de la altura, con un paso de al menos un texel
+            let texel_size = texture_manager.get_texture(bump_map).resolution().map_or(1e-3, |size| 1.0 / size as f32);
+            let step = footprint.max(texel_size);
+            // Desplazamiento en el mundo equivalente al paso en UV (para patrones en espacio del mundo)
+            let world_step = if intersect.uv_density > 0.0 { step / intersect.uv_density } else { 0.0 };
+            let height = |u: f32, v: f32, point: Vec3| {
+                let texel = self.sample_texture(bump_map, (u, v), &point, footprint, time, texture_manager);
+                (texel.red as f32 + texel.green as f32 + texel.blue as f32) / (3.0 * 255.0)
+            };
+            let (u, v, p) = (intersect.u, intersect.v, intersect.point);
+            let dh_du = (height(u + step, v, p + tangent * world_step) - height(u - step, v, p - tangent * world_step)) / (2.0 * step);
+            let dh_dv = (height(u, v + step, p + bitangent * world_step) - height(u, v - step, p - bitangent * world_step)) / (2.0 * step);
+
+            // Pendiente en unidades del mundo: altura por unidad UV * UV por unidad del mundo
+            let scale = self.bump_strength * intersect.uv_density;
+            normal = (normal - tangent * (dh_du * scale) - bitangent * (dh_dv * scale)).normalize();
+        }
+
+        normal
+    }
+
+    // Indica si el rayo debe atravesar la superficie en el punto de impacto
+    pub fn is_cut_out(&self, intersect: &Intersect, footprint: f32, time: f32, texture_manager: &TextureManager) -> bool {
+        match (self.alpha_cutoff, self.texture_index) {
+            (Some(cutoff), Some(texture_index)) => {
+                let texture = texture_manager.get_texture(texture_index);
+                let (u, v) = self.texture_uv(intersect.u, intersect.v, time);
+                let footprint = footprint * self.uv_transform.footprint_scale();
+                texture.sample_alpha(u, v, footprint, self.filter, self.wrap) < cutoff
+            }
+            _ => false,
         }
     }
 
@@ -112,27 +515,66 @@ impl Material {
         self.emissive_intensity > 0.0
     }
 
-    // Método para obtener la luz emisiva
-    pub fn get_emission(&self) -> Color {
-        if let Some(color) = self.emissive_color {
-            color * self.emissive_intensity
-        } else {
-            Color::black() // Si no tiene color emisivo, no emite luz
+    // Luz emitida en el punto de impacto: el color emisivo, teñido por el mapa de emisión si
+    // lo hay (sin color emisivo, el mapa emite sus propios colores), por la intensidad.
+    // Es radiancia sin recortar: con intensidades mayores que 1 pasa del blanco.
+    pub fn get_emission(&self, intersect: &Intersect, footprint: f32, time: f32, texture_manager: &TextureManager) -> Vec3 {
+        match (self.emission_map, self.emissive_color) {
+            (Some(emission_map), tint) => {
+                let texel = self.sample_texture(emission_map, (intersect.u, intersect.v), &intersect.point, footprint, time, texture_manager);
+                let tint = tint.map_or(Vec3::repeat(1.0), Color::to_vec3);
+                texel.to_vec3().component_mul(&tint) * self.emissive_intensity
+            }
+            (None, Some(color)) => color.to_vec3() * self.emissive_intensity,
+            (None, None) => Vec3::zeros(), // Si no tiene color emisivo, no emite luz
         }
     }
 
-    // Obtener el color difuso del material según las coordenadas de textura (u, v)
-    pub fn get_diffuse_color(&self, u: f32, v: f32, texture_manager: &TextureManager) -> Color {
+    // Obtener el color difuso del material en el punto de impacto (coordenadas UV o del mundo);
+    // `footprint` es el ancho en UV que cubre el rayo y decide el nivel de mipmap
+    pub fn get_diffuse_color(&self, intersect: &Intersect, footprint: f32, time: f32, texture_manager: &TextureManager) -> Color {
         if let Some(texture_index) = self.texture_index {
-            let texture = texture_manager.get_texture(texture_index);
-            let x = (u * (texture.width as f32 - 1.0)) as usize;
-            let y = ((1.0 - v) * (texture.height as f32 - 1.0)) as usize;
-            texture.get_color(x, y)
+            self.sample_texture(texture_index, (intersect.u, intersect.v), &intersect.point, footprint, time, texture_manager)
         } else {
             self.diffuse
         }
     }
 
+    // Parámetros del BRDF físico en el punto de impacto. Los materiales Phong se aproximan:
+    // rugosidad equivalente a su exponente y la reflectividad como espejo dieléctrico.
+    pub fn pbr_surface(&self, intersect: &Intersect, footprint: f32, time: f32, texture_manager: &TextureManager) -> PbrSurface {
+        let base_color = self.get_diffuse_color(intersect, footprint, time, texture_manager).to_vec3();
+
+        match self.shading {
+            ShadingModel::MetallicRoughness => {
+                let gray = |texture_index: Option<usize>| {
+                    texture_index.map_or(1.0, |index| {
+                        let texel = self.sample_texture(index, (intersect.u, intersect.v), &intersect.point, footprint, time, texture_manager);
+                        (texel.red as f32 + texel.green as f32 + texel.blue as f32) / (3.0 * 255.0)
+                    })
+                };
+                PbrSurface {
+                    base_color,
+                    metallic: self.metallic * gray(self.metallic_map),
+                    roughness: self.roughness * gray(self.roughness_map),
+                    f0: dielectric_f0(self.refraction_index),
+                }
+            }
+            ShadingModel::Phong if self.reflectivity > 0.0 => PbrSurface {
+                base_color: base_color * self.albedo[0],
+                metallic: 0.0,
+                roughness: 0.0,
+                f0: self.reflectivity,
+            },
+            ShadingModel::Phong => PbrSurface {
+                base_color: base_color * self.albedo[0],
+                metallic: 0.0,
+                roughness: phong_roughness(self.specular),
+                f0: dielectric_f0(1.5),
+            },
+        }
+    }
+
     pub fn black() -> Self {
         Material {
             diffuse: Color::new(0,0,0),
@@ -144,6 +586,139 @@ impl Material {
             texture_index: None, // No hay textura asociada
             emissive_color: None,
             emissive_intensity: 0.0,
+            emission_map: None,
+            filter: FilterMode::Trilinear,
+            wrap: WrapMode::Repeat,
+            uv_transform: UvTransform::identity(),
+            alpha_cutoff: None,
+            normal_map: None,
+            bump_map: None,
+            bump_strength: 0.0,
+            uv_scroll: Vec2::new(0.0, 0.0),
+            waves: None,
+            absorption_color: Color::new(255, 255, 255),
+            absorption_density: 0.0,
+            shading: ShadingModel::Phong,
+            metallic: 0.0,
+            roughness: 1.0,
+            metallic_map: None,
+            roughness_map: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uv_transform_scales_rotates_and_offsets() {
+        let transform = UvTransform::new(
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.5, 0.0),
+            std::f32::consts::FRAC_PI_2,
+        );
+        let (u, v) = transform.apply(0.25, 0.0);
+        assert!((u - 0.5).abs() < 1e-5);
+        assert!((v - 0.5).abs() < 1e-5);
+        assert_eq!(transform.footprint_scale(), 2.0);
+    }
+
+    #[test]
+    fn test_transmittance_decays_with_depth() {
+        let water = Material::new(Color::new(255, 255, 255), 0.0, [1.0, 0.0], 0.0, 1.0, 1.33)
+            .with_absorption(Color::new(51, 153, 230), 2.0);
+
+        // Tras 1/densidad unidades queda exactamente el color de absorción
+        let unit = water.transmittance(0.5);
+        assert!((unit[0] - 0.2).abs() < 1e-3);
+        assert!((unit[2] - 230.0 / 255.0).abs() < 1e-3);
+
+        // Más profundidad: más oscuro, y el azul sobrevive mejor que el rojo
+        let deep = water.transmittance(2.0);
+        assert!(deep[0] < unit[0] && deep[2] < unit[2]);
+        assert!(deep[2] > deep[0]);
+        assert_eq!(water.transmittance(0.0), [1.0; 3]);
+    }
+
+    #[test]
+    fn test_same_file_is_loaded_once() {
+        let mut texture_manager = TextureManager::new();
+        let first = texture_manager.load_texture("assets/wood.png").unwrap();
+        let second = texture_manager.load_texture("./assets/../assets/wood.png").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(texture_manager.count(), 1);
+    }
+
+    #[test]
+    fn test_missing_texture_uses_fallback_only_when_enabled() {
+        let mut strict = TextureManager::new();
+        assert!(strict.load_texture("assets/no-existe.png").is_err());
+        assert_eq!(strict.count(), 0);
+
+        let mut lenient = TextureManager::new().with_fallback();
+        let index = lenient.load_texture("assets/no-existe.png").unwrap();
+        assert_eq!(lenient.load_texture("assets/no-existe.png").unwrap(), index);
+        assert_eq!(lenient.texture_name(index), Some("assets/no-existe.png"));
+        let texel = lenient.get_texture(index).sample(0.0, 1.0, &Vec3::zeros(), 0.0, FilterMode::Nearest, WrapMode::Repeat);
+        assert_eq!(texel.to_hex(), 0xFF00FF);
+    }
+
+    #[test]
+    fn test_emission_map_is_tinted_by_emissive_color() {
+        use image::{DynamicImage, Rgb, RgbImage};
+
+        // Mitad izquierda encendida, mitad derecha apagada
+        let pattern = RgbImage::from_fn(2, 1, |x, _| if x == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) });
+        let mut texture_manager = TextureManager::new();
+        let index = texture_manager.add_texture(Texture::from_image(&DynamicImage::ImageRgb8(pattern)));
+
+        let mut material = Material::new(Color::new(255, 255, 255), 0.0, [1.0, 0.0], 0.0, 0.0, 1.0)
+            .with_emission_map(index);
+        material.filter = FilterMode::Nearest;
+        material.emissive_color = Some(Color::new(255, 128, 0));
+        assert!(material.is_emissive());
+
+        let lit = Intersect::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), 1.0, 0, 0.25, 0.5);
+        let dark = Intersect::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), 1.0, 0, 0.75, 0.5);
+        assert_eq!(Color::from_vec3(material.get_emission(&lit, 0.0, 0.0, &texture_manager)).to_hex(), 0xFF8000);
+        assert_eq!(Color::from_vec3(material.get_emission(&dark, 0.0, 0.0, &texture_manager)).to_hex(), 0x000000);
+    }
+
+    #[test]
+    fn test_roughness_map_scales_roughness() {
+        use image::{DynamicImage, Rgb, RgbImage};
+
+        let half_gray = RgbImage::from_pixel(2, 2, Rgb([128, 128, 128]));
+        let mut texture_manager = TextureManager::new();
+        let index = texture_manager.add_texture(Texture::from_image(&DynamicImage::ImageRgb8(half_gray)));
+
+        let material = Material::new_pbr(Color::new(255, 200, 100), 1.0, 0.8, 1.5).with_roughness_map(index);
+        let intersect = Intersect::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), 1.0, 0, 0.5, 0.5);
+        let surface = material.pbr_surface(&intersect, 0.0, 0.0, &texture_manager);
+        assert!((surface.roughness - 0.4).abs() < 0.01);
+        assert_eq!(surface.metallic, 1.0);
+        assert!((surface.base_color.y - 200.0 / 255.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_flat_normal_map_keeps_geometric_normal() {
+        use image::{DynamicImage, Rgb, RgbImage};
+
+        // (128, 128, 255) es la normal "sin perturbar" del espacio tangente
+        let flat = RgbImage::from_pixel(4, 4, Rgb([128, 128, 255]));
+        let mut texture_manager = TextureManager::new();
+        let index = texture_manager.add_texture(Texture::from_image(&DynamicImage::ImageRgb8(flat)));
+
+        let material = Material::new(Color::new(255, 255, 255), 0.0, [1.0, 0.0], 0.0, 0.0, 1.0)
+            .with_normal_map(index)
+            .with_bump_map(index, 0.1);
+        let mut intersect = Intersect::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), 1.0, 0, 0.5, 0.5);
+        intersect.tangent = Vec3::new(1.0, 0.0, 0.0);
+        intersect.bitangent = Vec3::new(0.0, 0.0, -1.0);
+        intersect.uv_density = 1.0;
+
+        let normal = material.shading_normal(&intersect, 0.0, 0.0, &texture_manager);
+        assert!((normal - Vec3::new(0.0, 1.0, 0.0)).norm() < 1e-2);
+    }
+}
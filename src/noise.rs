@@ -0,0 +1,194 @@
+use nalgebra_glm::Vec3;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+// Gradientes de las aristas de un cubo, compartidos por Perlin y simplex
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+];
+
+// Generador de ruido coherente con semilla, para resultados reproducibles
+#[derive(Debug, Clone)]
+pub struct Noise {
+    perm: Vec<usize>, // Permutación de 0..256 repetida dos veces
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut table: Vec<usize> = (0..256).collect();
+        table.shuffle(&mut StdRng::seed_from_u64(seed));
+        let perm = table.iter().chain(table.iter()).copied().collect();
+        Noise { perm }
+    }
+
+    fn hash(&self, x: i32, y: i32, z: i32) -> usize {
+        let x = (x & 255) as usize;
+        let y = (y & 255) as usize;
+        let z = (z & 255) as usize;
+        self.perm[x + self.perm[y + self.perm[z]]]
+    }
+
+    fn gradient_dot(&self, hash: usize, x: f32, y: f32, z: f32) -> f32 {
+        let g = GRADIENTS[hash % 12];
+        g[0] * x + g[1] * y + g[2] * z
+    }
+
+    // Ruido Perlin mejorado, en el rango aproximado [-1, 1]
+    pub fn perlin(&self, point: &Vec3) -> f32 {
+        let (xi, yi, zi) = (point.x.floor(), point.y.floor(), point.z.floor());
+        let (x, y, z) = (point.x - xi, point.y - yi, point.z - zi);
+        let (xi, yi, zi) = (xi as i32, yi as i32, zi as i32);
+
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let corner = |dx: i32, dy: i32, dz: i32| {
+            let hash = self.hash(xi + dx, yi + dy, zi + dz);
+            self.gradient_dot(hash, x - dx as f32, y - dy as f32, z - dz as f32)
+        };
+
+        lerp(
+            lerp(
+                lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+                lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+                v,
+            ),
+            lerp(
+                lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+                lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+                v,
+            ),
+            w,
+        )
+    }
+
+    // Ruido simplex 3D, en el rango aproximado [-1, 1]
+    pub fn simplex(&self, point: &Vec3) -> f32 {
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+
+        // Celda del simplex que contiene al punto
+        let s = (point.x + point.y + point.z) * F3;
+        let i = (point.x + s).floor();
+        let j = (point.y + s).floor();
+        let k = (point.z + s).floor();
+        let t = (i + j + k) * G3;
+        let x0 = point.x - (i - t);
+        let y0 = point.y - (j - t);
+        let z0 = point.z - (k - t);
+
+        // Orden de recorrido de las esquinas según el mayor componente
+        let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+            if y0 >= z0 { (1, 0, 0, 1, 1, 0) }
+            else if x0 >= z0 { (1, 0, 0, 1, 0, 1) }
+            else { (0, 0, 1, 1, 0, 1) }
+        } else if y0 < z0 { (0, 0, 1, 0, 1, 1) }
+        else if x0 < z0 { (0, 1, 0, 0, 1, 1) }
+        else { (0, 1, 0, 1, 1, 0) };
+
+        let corners = [
+            (0, 0, 0, x0, y0, z0),
+            (i1, j1, k1, x0 - i1 as f32 + G3, y0 - j1 as f32 + G3, z0 - k1 as f32 + G3),
+            (i2, j2, k2, x0 - i2 as f32 + 2.0 * G3, y0 - j2 as f32 + 2.0 * G3, z0 - k2 as f32 + 2.0 * G3),
+            (1, 1, 1, x0 - 1.0 + 3.0 * G3, y0 - 1.0 + 3.0 * G3, z0 - 1.0 + 3.0 * G3),
+        ];
+
+        let (i, j, k) = (i as i32, j as i32, k as i32);
+        let mut total = 0.0;
+        for (di, dj, dk, x, y, z) in corners {
+            let falloff = 0.6 - x * x - y * y - z * z;
+            if falloff > 0.0 {
+                let hash = self.hash(i + di, j + dj, k + dk);
+                total += falloff.powi(4) * self.gradient_dot(hash, x, y, z);
+            }
+        }
+
+        32.0 * total
+    }
+
+    // Ruido celular de Worley: distancia al punto característico más cercano, en [0, 1]
+    pub fn worley(&self, point: &Vec3) -> f32 {
+        let cell = Vec3::new(point.x.floor(), point.y.floor(), point.z.floor());
+        let mut nearest = f32::INFINITY;
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbor = cell + Vec3::new(dx as f32, dy as f32, dz as f32);
+                    let (nx, ny, nz) = (neighbor.x as i32, neighbor.y as i32, neighbor.z as i32);
+
+                    // Un punto por celda, con posición derivada de la permutación
+                    let feature = neighbor + Vec3::new(
+                        self.hash(nx, ny, nz) as f32 / 255.0,
+                        self.hash(nx + 17, ny + 31, nz + 47) as f32 / 255.0,
+                        self.hash(nx + 59, ny + 71, nz + 83) as f32 / 255.0,
+                    );
+                    nearest = nearest.min((feature - point).norm());
+                }
+            }
+        }
+
+        nearest.min(1.0)
+    }
+
+    // Movimiento browniano fraccionario: suma de octavas de Perlin, en el rango aproximado [-1, 1]
+    pub fn fbm(&self, point: &Vec3, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut normalization = 0.0;
+
+        for _ in 0..octaves {
+            total += self.perlin(&(point * frequency)) * amplitude;
+            normalization += amplitude;
+            amplitude *= gain;
+            frequency *= lacunarity;
+        }
+
+        if normalization > 0.0 { total / normalization } else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_points() -> impl Iterator<Item = Vec3> {
+        (0..200).map(|i| {
+            let t = i as f32 * 0.37;
+            Vec3::new(t.sin() * 7.3, t * 0.53, (t * 1.7).cos() * 4.1)
+        })
+    }
+
+    #[test]
+    fn test_same_seed_is_reproducible() {
+        let a = Noise::new(42);
+        let b = Noise::new(42);
+        let c = Noise::new(7);
+        let point = Vec3::new(1.3, 2.7, -0.4);
+        assert_eq!(a.perlin(&point), b.perlin(&point));
+        assert_eq!(a.worley(&point), b.worley(&point));
+        assert_ne!(a.perlin(&point), c.perlin(&point));
+    }
+
+    #[test]
+    fn test_noise_ranges() {
+        let noise = Noise::new(1);
+        for point in sample_points() {
+            assert!(noise.perlin(&point).abs() <= 1.01);
+            assert!(noise.simplex(&point).abs() <= 1.01);
+            assert!((0.0..=1.0).contains(&noise.worley(&point)));
+            assert!(noise.fbm(&point, 5, 2.0, 0.5).abs() <= 1.01);
+        }
+    }
+
+    #[test]
+    fn test_perlin_is_zero_on_lattice() {
+        let noise = Noise::new(3);
+        assert_eq!(noise.perlin(&Vec3::new(2.0, -5.0, 11.0)), 0.0);
+    }
+}
@@ -0,0 +1,80 @@
+use nalgebra_glm::Vec3;
+
+// Índice de refracción del aire (medio exterior de todos los objetos)
+pub const AIR_IOR: f32 = 1.0;
+
+pub fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
+    incident - 2.0 * incident.dot(normal) * normal
+}
+
+// Dirección refractada según la ley de Snell. `normal` debe apuntar hacia el lado
+// del que viene el rayo y `eta` es n_incidente / n_transmitido.
+// Devuelve None si hay reflexión interna total.
+pub fn refract(incident: &Vec3, normal: &Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = (-incident.dot(normal)).clamp(-1.0, 1.0);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(eta * incident + (eta * cos_i - cos_t) * normal)
+}
+
+// Aproximación de Schlick para la reflectancia a un ángulo con coseno `cos_i`
+pub fn fresnel_schlick(cos_i: f32, f0: f32) -> f32 {
+    f0 + (1.0 - f0) * (1.0 - cos_i.clamp(0.0, 1.0)).powi(5)
+}
+
+// Reflectancia exacta de un dieléctrico (promedio de las polarizaciones s y p).
+// `cos_i` es el coseno del ángulo de incidencia; eta_i y eta_t son los índices de
+// ambos medios. Con reflexión interna total devuelve 1.0.
+pub fn fresnel_dielectric(cos_i: f32, eta_i: f32, eta_t: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin_t = eta_i / eta_t * (1.0 - cos_i * cos_i).max(0.0).sqrt();
+    if sin_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_t * sin_t).max(0.0).sqrt();
+    let r_s = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+    let r_p = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+    (r_s * r_s + r_p * r_p) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresnel_grows_at_grazing_angles() {
+        let head_on = fresnel_dielectric(1.0, AIR_IOR, 1.33);
+        let grazing = fresnel_dielectric(0.05, AIR_IOR, 1.33);
+        assert!((head_on - 0.02).abs() < 0.005); // ~2% para el agua vista de frente
+        assert!(grazing > 0.6);
+
+        // Schlick se aproxima a la exacta con el mismo f0
+        let f0 = ((1.33 - 1.0) / (1.33 + 1.0_f32)).powi(2);
+        assert!((fresnel_schlick(0.5, f0) - fresnel_dielectric(0.5, AIR_IOR, 1.33)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_total_internal_reflection() {
+        // Desde el agua hacia el aire, más allá del ángulo crítico (~48.8°)
+        let cos_i = 60.0_f32.to_radians().cos();
+        assert_eq!(fresnel_dielectric(cos_i, 1.33, AIR_IOR), 1.0);
+
+        let incident = Vec3::new(60.0_f32.to_radians().sin(), -cos_i, 0.0);
+        assert!(refract(&incident, &Vec3::new(0.0, 1.0, 0.0), 1.33).is_none());
+    }
+
+    #[test]
+    fn test_refraction_bends_toward_normal_when_entering() {
+        let incident = Vec3::new(1.0, -1.0, 0.0).normalize();
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let refracted = refract(&incident, &normal, AIR_IOR / 1.33).unwrap();
+
+        // Snell: sin_t = sin_i / 1.33, y el rayo sigue avanzando hacia abajo
+        assert!((refracted.norm() - 1.0).abs() < 1e-5);
+        assert!((refracted.x - incident.x / 1.33).abs() < 1e-5);
+        assert!(refracted.y < 0.0);
+    }
+}
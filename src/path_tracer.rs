@@ -0,0 +1,140 @@
+use nalgebra_glm::Vec3;
+use rand::Rng;
+use crate::materials::Material;
+use crate::optics::{reflect, refract, fresnel_dielectric, AIR_IOR};
+use crate::ray_intersect::RayCone;
+use crate::sampling::cosine_hemisphere;
+use crate::tracing::{RenderContext, scene_intersect, sky_radiance, cloud_transmittance};
+use std::f32::consts::PI;
+
+// Rebotes máximos de un camino
+const MAX_BOUNCES: u32 = 8;
+// A partir de este rebote los caminos con poca energía se terminan al azar (ruleta rusa)
+const ROULETTE_START: u32 = 3;
+
+// Radiancia (lineal, 1.0 = 255) que llega por el rayo, estimada con un camino aleatorio.
+// En cada rebote se suma la luz directa de las luces puntuales y se sigue un único lóbulo
+// del BRDF elegido al azar; el cielo (con sus nubes) ilumina la escena como una luz de ambiente.
+pub fn trace_path<R: Rng>(
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    context: &RenderContext,
+    cone: RayCone,
+    rng: &mut R,
+) -> Vec3 {
+    let epsilon = 1e-3;
+    let mut radiance = Vec3::zeros();
+    let mut throughput = Vec3::repeat(1.0);
+    let mut origin = *ray_origin;
+    let mut direction = *ray_direction;
+    let mut cone = cone;
+    let mut medium: Option<Material> = None; // Volumen en el que viaja el rayo (None = aire)
+
+    for bounce in 0..MAX_BOUNCES {
+        let intersect = scene_intersect(&origin, &direction, context, cone);
+
+        // Niebla del tramo en el aire: suma la luz del sol que dispersa y atenúa lo que hay detrás
+        if let (None, Some(fog)) = (medium, &context.fog) {
+            let distance = if intersect.is_intersecting { intersect.distance } else { f32::INFINITY };
+            let (transmittance, inscattered) = fog.march(&origin, &direction, distance, context, cone, rng.gen());
+            radiance += throughput.component_mul(&inscattered);
+            throughput *= transmittance;
+        }
+
+        if !intersect.is_intersecting {
+            radiance += throughput.component_mul(&sky_radiance(&origin, &direction, context));
+            break;
+        }
+
+        // Absorción del tramo recorrido dentro de un volumen (Beer–Lambert)
+        if let Some(medium) = medium {
+            let [r, g, b] = medium.transmittance(intersect.distance);
+            throughput.component_mul_assign(&Vec3::new(r, g, b));
+        }
+
+        let material = *context.materials.get(intersect.material_id);
+        let front_face = direction.dot(&intersect.normal) < 0.0;
+        let facing = if front_face { 1.0 } else { -1.0 };
+        let geometric_normal = intersect.normal * facing;
+        let footprint = cone.uv_footprint(&intersect, &direction);
+        let normal = material.shading_normal(&intersect, footprint, context.time, context.texture_manager) * facing;
+        let view_dir = -direction;
+        cone = cone.propagate(intersect.distance);
+
+        if material.is_emissive() {
+            radiance += throughput.component_mul(&material.get_emission(&intersect, footprint, context.time, context.texture_manager));
+        }
+
+        // La parte transparente se comporta como un dieléctrico liso: reflexión o refracción
+        // elegidas con la probabilidad de Fresnel, sin cambiar el peso del camino
+        if material.transparency > 0.0 && rng.gen::<f32>() < material.transparency {
+            let (eta_i, eta_t) = if front_face {
+                (AIR_IOR, material.refraction_index)
+            } else {
+                (material.refraction_index, AIR_IOR)
+            };
+            let fresnel = fresnel_dielectric(normal.dot(&view_dir).max(0.0), eta_i, eta_t);
+
+            match refract(&direction, &normal, eta_i / eta_t) {
+                Some(refract_dir) if rng.gen::<f32>() >= fresnel => {
+                    origin = intersect.point - geometric_normal * epsilon;
+                    direction = refract_dir.normalize();
+                    medium = if front_face { Some(material) } else { None };
+                }
+                _ => {
+                    origin = intersect.point + geometric_normal * epsilon;
+                    direction = reflect(&direction, &normal).normalize();
+                }
+            }
+            continue;
+        }
+
+        let surface = material.pbr_surface(&intersect, footprint, context.time, context.texture_manager);
+        origin = intersect.point + geometric_normal * epsilon;
+
+        // Luz directa de las luces puntuales (no pueden alcanzarse por azar)
+        for light in context.lights {
+            let to_light = light.position - intersect.point;
+            let light_dir = to_light.normalize();
+            let shadow = scene_intersect(&origin, &light_dir, context, cone);
+            if shadow.is_intersecting && shadow.distance < to_light.norm() {
+                continue;
+            }
+            let light_radiance = light.color.to_vec3() * (light.intensity * cloud_transmittance(&origin, light, context) * PI);
+            let contribution = surface.evaluate(&normal, &view_dir, &light_dir).component_mul(&light_radiance);
+            radiance += throughput.component_mul(&contribution);
+        }
+
+        // Siguiente dirección: lóbulo especular (GGX) o difuso (coseno)
+        let specular_probability = surface.specular_probability(&normal, &view_dir);
+        let (u1, u2) = (rng.gen::<f32>(), rng.gen::<f32>());
+        if rng.gen::<f32>() < specular_probability {
+            match surface.sample_specular(&normal, &view_dir, u1, u2) {
+                Some((reflect_dir, weight)) => {
+                    direction = reflect_dir;
+                    throughput.component_mul_assign(&(weight / specular_probability));
+                }
+                None => break,
+            }
+        } else {
+            direction = cosine_hemisphere(&normal, u1, u2);
+            let weight = surface.diffuse_weight(&normal, &view_dir);
+            throughput.component_mul_assign(&(weight / (1.0 - specular_probability)));
+        }
+
+        // La normal de sombreado puede mandar el rayo por debajo de la superficie real
+        if direction.dot(&geometric_normal) <= 0.0 {
+            break;
+        }
+
+        if bounce >= ROULETTE_START {
+            let survival = throughput.max().clamp(0.05, 0.95);
+            if rng.gen::<f32>() > survival {
+                break;
+            }
+            throughput /= survival;
+        }
+    }
+
+    radiance
+}
@@ -0,0 +1,214 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::sampling::orthonormal_basis;
+
+// Por debajo de esta rugosidad la GGX es prácticamente un espejo y se trata como tal
+pub const MIN_ROUGHNESS: f32 = 0.03;
+
+// Reflectancia a incidencia normal de un dieléctrico de índice `ior` rodeado de aire
+pub fn dielectric_f0(ior: f32) -> f32 {
+    ((ior - 1.0) / (ior + 1.0)).powi(2)
+}
+
+// Rugosidad equivalente a un exponente de Phong, para tratar los materiales antiguos
+// con el mismo modelo (alpha de Beckmann = sqrt(2 / (n + 2)) y alpha = rugosidad²)
+pub fn phong_roughness(exponent: f32) -> f32 {
+    (2.0 / (exponent.max(0.0) + 2.0)).powf(0.25)
+}
+
+// Distribución de normales de Trowbridge–Reitz (GGX)
+fn ggx_distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * denominator * denominator)
+}
+
+// Término de sombreado/enmascaramiento de Smith para una sola dirección
+fn smith_g1(n_dot_x: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    2.0 * n_dot_x / (n_dot_x + (alpha2 + (1.0 - alpha2) * n_dot_x * n_dot_x).sqrt())
+}
+
+// Normal de microfaceta distribuida según D(h) * (n·h)
+fn sample_ggx(normal: &Vec3, alpha: f32, u1: f32, u2: f32) -> Vec3 {
+    let alpha2 = alpha * alpha;
+    let cos_theta = ((1.0 - u1) / (1.0 + (alpha2 - 1.0) * u1)).max(0.0).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + normal * cos_theta).normalize()
+}
+
+fn average(color: &Vec3) -> f32 {
+    (color.x + color.y + color.z) / 3.0
+}
+
+// Parámetros del BRDF metálico/rugoso en un punto, con las texturas ya aplicadas
+#[derive(Debug, Clone, Copy)]
+pub struct PbrSurface {
+    pub base_color: Vec3, // Color base lineal en [0, 1]
+    pub metallic: f32,    // 0 = dieléctrico, 1 = metal
+    pub roughness: f32,   // 0 = pulido, 1 = completamente mate
+    pub f0: f32,          // Reflectancia especular de la parte dieléctrica a incidencia normal
+}
+
+impl PbrSurface {
+    fn alpha(&self) -> f32 {
+        let roughness = self.roughness.clamp(MIN_ROUGHNESS, 1.0);
+        roughness * roughness
+    }
+
+    // Indica si la superficie es lo bastante pulida para reflejar con un solo rayo
+    pub fn is_mirror(&self) -> bool {
+        self.roughness < MIN_ROUGHNESS
+    }
+
+    // Color especular a incidencia normal: los metales reflejan con su color base
+    pub fn specular_color(&self) -> Vec3 {
+        Vec3::repeat(self.f0).lerp(&self.base_color, self.metallic)
+    }
+
+    // Aproximación de Schlick por canal
+    pub fn fresnel(&self, cos_theta: f32) -> Vec3 {
+        let f0 = self.specular_color();
+        let weight = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+        f0 + (Vec3::repeat(1.0) - f0) * weight
+    }
+
+    // Albedo de la capa difusa: la luz que no reflejó la capa especular; los metales no tienen
+    fn diffuse_color(&self, n_dot_v: f32) -> Vec3 {
+        let transmitted = Vec3::repeat(1.0) - self.fresnel(n_dot_v);
+        transmitted.component_mul(&self.base_color) * (1.0 - self.metallic)
+    }
+
+    // BRDF por el coseno de la luz: fracción de la luz que llega desde `light` que se
+    // refleja hacia `view` (ambas direcciones salen de la superficie)
+    pub fn evaluate(&self, normal: &Vec3, view: &Vec3, light: &Vec3) -> Vec3 {
+        let (diffuse, specular) = self.evaluate_lobes(normal, view, light);
+        diffuse + specular
+    }
+
+    // Lo mismo que `evaluate`, separado en la parte difusa y la especular
+    pub fn evaluate_lobes(&self, normal: &Vec3, view: &Vec3, light: &Vec3) -> (Vec3, Vec3) {
+        let n_dot_l = normal.dot(light);
+        let n_dot_v = normal.dot(view);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return (Vec3::zeros(), Vec3::zeros());
+        }
+
+        let half = (view + light).normalize();
+        let n_dot_h = normal.dot(&half).max(0.0);
+        let v_dot_h = view.dot(&half).max(0.0);
+        let alpha = self.alpha();
+
+        let geometry = smith_g1(n_dot_v, alpha) * smith_g1(n_dot_l, alpha);
+        let specular = self.fresnel(v_dot_h) * (ggx_distribution(n_dot_h, alpha) * geometry / (4.0 * n_dot_v * n_dot_l));
+        let diffuse = self.diffuse_color(n_dot_v) / PI;
+        (diffuse * n_dot_l, specular * n_dot_l)
+    }
+
+    // Dirección reflejada muestreada con la distribución GGX (importance sampling) y su
+    // peso, BRDF * coseno / pdf. None si la microfaceta refleja hacia dentro de la superficie.
+    pub fn sample_specular(&self, normal: &Vec3, view: &Vec3, u1: f32, u2: f32) -> Option<(Vec3, Vec3)> {
+        let alpha = self.alpha();
+        let half = sample_ggx(normal, alpha, u1, u2);
+        let v_dot_h = view.dot(&half);
+        let light = half * (2.0 * v_dot_h) - view;
+
+        let n_dot_l = normal.dot(&light);
+        let n_dot_v = normal.dot(view);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 || v_dot_h <= 0.0 {
+            return None;
+        }
+
+        // Con pdf = D (n·h) / (4 v·h), la D se cancela: peso = F G (v·h) / ((n·v) (n·h))
+        let n_dot_h = normal.dot(&half).max(1e-6);
+        let geometry = smith_g1(n_dot_v, alpha) * smith_g1(n_dot_l, alpha);
+        let weight = self.fresnel(v_dot_h) * (geometry * v_dot_h / (n_dot_v * n_dot_h));
+        Some((light.normalize(), weight))
+    }
+
+    // Peso del lóbulo difuso cuando se muestrea con densidad de coseno (BRDF * coseno / pdf)
+    pub fn diffuse_weight(&self, normal: &Vec3, view: &Vec3) -> Vec3 {
+        self.diffuse_color(normal.dot(view).max(0.0))
+    }
+
+    // Probabilidad de seguir el lóbulo especular cuando solo se traza un rayo por rebote
+    pub fn specular_probability(&self, normal: &Vec3, view: &Vec3) -> f32 {
+        let n_dot_v = normal.dot(view).max(0.0);
+        let specular = average(&self.fresnel(n_dot_v));
+        let diffuse = average(&self.diffuse_color(n_dot_v));
+        if diffuse <= 0.0 {
+            1.0
+        } else {
+            (specular / (specular + diffuse)).clamp(0.1, 0.9)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::{cosine_hemisphere, hammersley};
+
+    fn surface(base: f32, metallic: f32, roughness: f32) -> PbrSurface {
+        PbrSurface { base_color: Vec3::repeat(base), metallic, roughness, f0: dielectric_f0(1.5) }
+    }
+
+    #[test]
+    fn test_dielectric_f0() {
+        assert!((dielectric_f0(1.5) - 0.04).abs() < 1e-4);
+        assert!((dielectric_f0(1.33) - 0.02).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_white_surface_does_not_create_energy() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let view = Vec3::new(0.6, 0.8, 0.0);
+        let count = 4096;
+
+        for (metallic, roughness) in [(0.0, 0.2), (0.0, 1.0), (1.0, 0.5)] {
+            let surface = surface(1.0, metallic, roughness);
+            // Estimador de Monte Carlo del albedo con muestras de coseno (pdf = cos / pi)
+            let albedo: f32 = (0..count)
+                .map(|index| {
+                    let (u1, u2) = hammersley(index, count);
+                    let light = cosine_hemisphere(&normal, u1, u2);
+                    let cos = normal.dot(&light).max(1e-4);
+                    surface.evaluate(&normal, &view, &light).x * PI / cos
+                })
+                .sum::<f32>() / count as f32;
+            assert!(albedo <= 1.02, "albedo {} con metálico {} y rugosidad {}", albedo, metallic, roughness);
+        }
+    }
+
+    #[test]
+    fn test_importance_sampling_matches_evaluation() {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let view = Vec3::new(0.0, 0.5, 0.866).normalize();
+        let metal = surface(0.9, 1.0, 0.5);
+        let count = 8192;
+
+        // El mismo albedo estimado de dos formas: muestreando la GGX o el coseno
+        let mut sampled = 0.0;
+        let mut uniform = 0.0;
+        for index in 0..count {
+            let (u1, u2) = hammersley(index, count);
+            if let Some((_, weight)) = metal.sample_specular(&normal, &view, u1, u2) {
+                sampled += weight.x;
+            }
+            let light = cosine_hemisphere(&normal, u1, u2);
+            uniform += metal.evaluate(&normal, &view, &light).x * PI / normal.dot(&light).max(1e-4);
+        }
+        let (sampled, uniform) = (sampled / count as f32, uniform / count as f32);
+        assert!((sampled - uniform).abs() < 0.03, "{} vs {}", sampled, uniform);
+    }
+
+    #[test]
+    fn test_metals_reflect_with_base_color() {
+        let gold = PbrSurface { base_color: Vec3::new(1.0, 0.78, 0.34), metallic: 1.0, roughness: 0.3, f0: 0.04 };
+        assert_eq!(gold.specular_color(), gold.base_color);
+        assert_eq!(gold.diffuse_weight(&Vec3::new(0.0, 1.0, 0.0), &Vec3::new(0.0, 1.0, 0.0)), Vec3::zeros());
+        assert_eq!(gold.specular_probability(&Vec3::new(0.0, 1.0, 0.0), &Vec3::new(0.0, 1.0, 0.0)), 1.0);
+    }
+}
@@ -0,0 +1,128 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::noise::Noise;
+
+// Espacio en el que se evalúa el patrón
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureSpace {
+    Uv,    // Coordenadas (u, v) de la superficie; se mueve con el objeto
+    World, // Posición del punto en el mundo; el patrón atraviesa los objetos como un bloque sólido
+}
+
+// Tipo de ruido base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseKind {
+    Perlin,
+    Simplex,
+    Worley,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Pattern {
+    // Tablero de ajedrez con casillas de `size` unidades
+    Checker { even: Color, odd: Color, size: f32 },
+    // Degradado lineal a lo largo de `direction`, de 0 a 1 unidades
+    Gradient { start: Color, end: Color, direction: Vec3 },
+    // Ruido simple mapeado entre dos colores
+    Noise { kind: NoiseKind, low: Color, high: Color, scale: f32 },
+    // Varias octavas de Perlin
+    Fbm { low: Color, high: Color, scale: f32, octaves: u32 },
+    // Anillos concéntricos alrededor del eje Y, deformados por ruido
+    Wood { light: Color, dark: Color, rings: f32, turbulence: f32 },
+    // Vetas sinusoidales a lo largo del eje X, deformadas por ruido
+    Marble { light: Color, dark: Color, scale: f32, turbulence: f32 },
+}
+
+// Textura calculada a partir de una fórmula en lugar de una imagen
+#[derive(Debug, Clone)]
+pub struct ProceduralTexture {
+    pub pattern: Pattern,
+    pub space: TextureSpace,
+    noise: Noise,
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    a * (1.0 - t) + b * t
+}
+
+impl ProceduralTexture {
+    pub fn new(pattern: Pattern, space: TextureSpace, seed: u64) -> Self {
+        ProceduralTexture {
+            pattern,
+            space,
+            noise: Noise::new(seed),
+        }
+    }
+
+    // Evalúa el patrón en las coordenadas (u, v) o en el punto del mundo, según `space`
+    pub fn evaluate(&self, u: f32, v: f32, point: &Vec3) -> Color {
+        let p = match self.space {
+            TextureSpace::Uv => Vec3::new(u, v, 0.0),
+            TextureSpace::World => *point,
+        };
+
+        match self.pattern {
+            Pattern::Checker { even, odd, size } => {
+                let cells = (p.x / size).floor() + (p.y / size).floor() + (p.z / size).floor();
+                if (cells as i64).rem_euclid(2) == 0 { even } else { odd }
+            }
+            Pattern::Gradient { start, end, direction } => mix(start, end, p.dot(&direction)),
+            Pattern::Noise { kind, low, high, scale } => {
+                let p = p * scale;
+                let t = match kind {
+                    NoiseKind::Perlin => self.noise.perlin(&p) * 0.5 + 0.5,
+                    NoiseKind::Simplex => self.noise.simplex(&p) * 0.5 + 0.5,
+                    NoiseKind::Worley => self.noise.worley(&p),
+                };
+                mix(low, high, t)
+            }
+            Pattern::Fbm { low, high, scale, octaves } => {
+                let t = self.noise.fbm(&(p * scale), octaves, 2.0, 0.5) * 0.5 + 0.5;
+                mix(low, high, t)
+            }
+            Pattern::Wood { light, dark, rings, turbulence } => {
+                let distortion = self.noise.fbm(&(p * 4.0), 3, 2.0, 0.5) * turbulence;
+                let radius = (p.x * p.x + p.z * p.z).sqrt() * rings + distortion;
+                // Anillos suaves: claro en el centro de cada anillo, oscuro en el borde
+                let ring = (radius.fract() * std::f32::consts::PI).sin();
+                mix(dark, light, ring)
+            }
+            Pattern::Marble { light, dark, scale, turbulence } => {
+                let distortion = self.noise.fbm(&(p * scale), 5, 2.0, 0.5) * turbulence;
+                let vein = ((p.x * scale + distortion) * std::f32::consts::PI).sin() * 0.5 + 0.5;
+                mix(dark, light, vein.powf(0.5))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checker_alternates() {
+        let texture = ProceduralTexture::new(
+            Pattern::Checker { even: Color::new(255, 255, 255), odd: Color::black(), size: 0.5 },
+            TextureSpace::World,
+            0,
+        );
+        assert_eq!(texture.evaluate(0.0, 0.0, &Vec3::new(0.25, 0.25, 0.25)).red, 255);
+        assert_eq!(texture.evaluate(0.0, 0.0, &Vec3::new(0.75, 0.25, 0.25)).red, 0);
+        assert_eq!(texture.evaluate(0.0, 0.0, &Vec3::new(-0.25, 0.25, 0.25)).red, 0);
+    }
+
+    #[test]
+    fn test_uv_space_ignores_world_position() {
+        let texture = ProceduralTexture::new(
+            Pattern::Gradient { start: Color::black(), end: Color::new(200, 200, 200), direction: Vec3::new(1.0, 0.0, 0.0) },
+            TextureSpace::Uv,
+            0,
+        );
+        let a = texture.evaluate(0.5, 0.2, &Vec3::new(10.0, 0.0, 0.0));
+        let b = texture.evaluate(0.5, 0.9, &Vec3::new(-3.0, 4.0, 1.0));
+        assert_eq!(a.red, 100);
+        assert_eq!(a.to_hex(), b.to_hex());
+    }
+}
@@ -1,5 +1,4 @@
 use nalgebra_glm::Vec3;
-use crate::materials::Material;
 
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code)]
@@ -8,20 +7,28 @@ pub struct Intersect {
     pub normal: Vec3,
     pub distance: f32,
     pub is_intersecting: bool,
-    pub material: Material,
+    pub material_id: usize, // Índice del material en MaterialLibrary
+    pub object_id: usize,   // Índice del objeto de la escena que se golpeó
     pub u: f32,
     pub v: f32,
+    pub uv_density: f32, // Cambio de las UV por unidad de distancia en el mundo
+    pub tangent: Vec3,   // Dirección en la que crece u sobre la superficie
+    pub bitangent: Vec3, // Dirección en la que crece v sobre la superficie
 }
 
 impl Intersect {
-    pub fn new(point: Vec3, normal: Vec3, distance: f32, material: Material, u:f32, v:f32) -> Self {
+    pub fn new(point: Vec3, normal: Vec3, distance: f32, material_id: usize, u:f32, v:f32) -> Self {
         Intersect {
             point,
             normal,
             distance,
             is_intersecting: true,
-            material,
+            material_id,
+            object_id: 0,
             u, v,
+            uv_density: 0.0,
+            tangent: Vec3::zeros(),
+            bitangent: Vec3::zeros(),
         }
     }
 
@@ -31,13 +38,53 @@ impl Intersect {
             normal: Vec3::zeros(),
             distance: 0.0,
             is_intersecting: false,
-            material: Material::black(),
+            material_id: 0,
+            object_id: 0,
             u: 0.0,
             v: 0.0,
+            uv_density: 0.0,
+            tangent: Vec3::zeros(),
+            bitangent: Vec3::zeros(),
         }
     }
 }
 
-pub trait RayIntersect {
+// Cono que envuelve al rayo, para estimar cuánta superficie cubre cada píxel
+#[derive(Debug, Clone, Copy)]
+pub struct RayCone {
+    pub width: f32,        // Ancho del cono en el origen del rayo
+    pub spread_angle: f32, // Ángulo de apertura por unidad de distancia
+}
+
+impl RayCone {
+    // Cono de un rayo primario: nace en la cámara y se abre lo que mide un píxel
+    pub fn from_pixel(fov: f32, height: f32) -> Self {
+        RayCone {
+            width: 0.0,
+            spread_angle: (2.0 * (fov / 2.0).tan() / height).atan(),
+        }
+    }
+
+    pub fn width_at(&self, distance: f32) -> f32 {
+        self.width + self.spread_angle * distance
+    }
+
+    // Cono que continúa desde el punto de impacto (reflexión o refracción en superficies planas)
+    pub fn propagate(&self, distance: f32) -> Self {
+        RayCone {
+            width: self.width_at(distance),
+            spread_angle: self.spread_angle,
+        }
+    }
+
+    // Ancho de la huella en unidades UV, considerando la inclinación de la superficie
+    pub fn uv_footprint(&self, intersect: &Intersect, ray_direction: &Vec3) -> f32 {
+        let cos_theta = intersect.normal.dot(ray_direction).abs().max(1e-2);
+        self.width_at(intersect.distance) / cos_theta * intersect.uv_density
+    }
+}
+
+// Send + Sync para poder compartir los objetos entre los hilos de Rayon
+pub trait RayIntersect: Send + Sync {
     fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect;
 }
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+// Resolución interna de la vista previa mientras se mueve la cámara. Después de cada
+// cuadro ajusta la escala para que el siguiente tarde lo que dice el presupuesto: el
+// tiempo de un cuadro es proporcional a la cantidad de píxeles, es decir a 1 / escala².
+#[derive(Debug, Clone, Copy)]
+pub struct DynamicResolution {
+    pub budget: Duration, // Tiempo de cuadro deseado mientras se navega
+    pub max_scale: f32,   // Reducción máxima de cada lado de la imagen
+    scale: f32,           // 1 = resolución completa, 2 = la mitad de ancho y de alto...
+}
+
+impl DynamicResolution {
+    pub fn new(budget: Duration) -> Self {
+        DynamicResolution {
+            budget,
+            max_scale: 16.0,
+            scale: 4.0,
+        }
+    }
+
+    // Tamaño en que se renderiza una imagen de `width` x `height`
+    pub fn internal_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let scaled = |size: usize| ((size as f32 / self.scale).round() as usize).clamp(1, size);
+        (scaled(width), scaled(height))
+    }
+
+    // Ajusta la escala según lo que tardó el último cuadro a la escala actual. Se mueve solo
+    // la mitad del camino para que un cuadro atípico no haga saltar la resolución.
+    pub fn update(&mut self, frame_time: Duration) {
+        let ratio = frame_time.as_secs_f32() / self.budget.as_secs_f32().max(1e-4);
+        let target = (self.scale * ratio.sqrt()).clamp(1.0, self.max_scale);
+        self.scale = (self.scale + target) / 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_frames_lower_the_resolution() {
+        let mut resolution = DynamicResolution::new(Duration::from_millis(50));
+        let before = resolution.scale;
+        resolution.update(Duration::from_millis(200));
+        assert!(resolution.scale > before);
+
+        let (width, height) = resolution.internal_size(800, 600);
+        assert!(width < 800 / before as usize && height < 600 / before as usize);
+    }
+
+    #[test]
+    fn test_scale_converges_to_the_budget() {
+        // Un cuadro a resolución completa tarda 800 ms; con 50 ms la escala ideal es 4
+        let mut resolution = DynamicResolution::new(Duration::from_millis(50));
+        for _ in 0..20 {
+            let frame_time = 0.8 / (resolution.scale * resolution.scale);
+            resolution.update(Duration::from_secs_f32(frame_time));
+        }
+        assert!((resolution.scale - 4.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_scale_is_clamped() {
+        let mut resolution = DynamicResolution { max_scale: 8.0, ..DynamicResolution::new(Duration::from_millis(50)) };
+        for _ in 0..20 {
+            resolution.update(Duration::from_secs(10));
+        }
+        assert!(resolution.scale <= 8.0);
+        for _ in 0..20 {
+            resolution.update(Duration::ZERO);
+        }
+        assert!(resolution.scale < 1.001);
+        assert_eq!(resolution.internal_size(800, 600), (800, 600));
+    }
+}
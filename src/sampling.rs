@@ -0,0 +1,66 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Dos vectores perpendiculares a `normal` (y entre sí) para construir direcciones locales
+pub fn orthonormal_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let helper = if normal.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+// Dirección del hemisferio alrededor de `normal` con densidad proporcional al coseno.
+// `u1` y `u2` son números uniformes en [0, 1).
+pub fn cosine_hemisphere(normal: &Vec3, u1: f32, u2: f32) -> Vec3 {
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let radius = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let z = (1.0 - u1).max(0.0).sqrt();
+    (tangent * (radius * phi.cos()) + bitangent * (radius * phi.sin()) + normal * z).normalize()
+}
+
+// Punto `index` de la secuencia de Hammersley de `count` puntos: muestras bien
+// repartidas en el cuadrado unitario sin necesidad de un generador aleatorio
+pub fn hammersley(index: u32, count: u32) -> (f32, f32) {
+    let radical_inverse = index.reverse_bits() as f32 / 4_294_967_296.0;
+    ((index as f32 + 0.5) / count as f32, radical_inverse)
+}
+
+// Desplazamiento pseudoaleatorio en [0, 1)² derivado de una posición, para que puntos
+// vecinos usen patrones de muestreo distintos (rotación de Cranley–Patterson)
+pub fn hash_offset(point: &Vec3) -> (f32, f32) {
+    let mut hash = point.x.to_bits().wrapping_mul(0x9E37_79B9)
+        ^ point.y.to_bits().wrapping_mul(0x85EB_CA6B)
+        ^ point.z.to_bits().wrapping_mul(0xC2B2_AE35);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    let first = (hash & 0xFFFF) as f32 / 65_536.0;
+    let second = (hash >> 16) as f32 / 65_536.0;
+    (first, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_samples_stay_in_hemisphere() {
+        let normal = Vec3::new(0.3, 0.9, -0.2).normalize();
+        for index in 0..64 {
+            let (u1, u2) = hammersley(index, 64);
+            let direction = cosine_hemisphere(&normal, u1, u2);
+            assert!((direction.norm() - 1.0).abs() < 1e-4);
+            assert!(direction.dot(&normal) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_hammersley_is_in_unit_square() {
+        for index in 0..16 {
+            let (a, b) = hammersley(index, 16);
+            assert!((0.0..1.0).contains(&a) && (0.0..1.0).contains(&b));
+        }
+        assert_eq!(hammersley(1, 4).1, 0.5);
+    }
+}
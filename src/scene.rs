@@ -1,11 +1,17 @@
 use crate::light::Light;
+use crate::ambient::SkyLight;
+use crate::clouds::CloudLayer;
 use crate::color::Color;
+use crate::fog::Fog;
 use nalgebra_glm::Vec3;
 
 pub struct Scene {
     pub time_of_day: f32, // Valor entre 0.0 (medianoche) y 1.0 (medianoche siguiente)
     pub light: Light,
     pub cycle_duration: f32, 
+    pub fog: Option<Fog>, // Niebla a mediodía; se ajusta a la hora del día al renderizar
+    pub clouds: Option<CloudLayer>,
+    pub sky_light: Option<SkyLight>,
 }
 
 impl Scene {
@@ -18,9 +24,17 @@ impl Scene {
                 intensity: 1.0,                       // Intensidad inicial
             },
             cycle_duration,
+            fog: None,
+            clouds: None,
+            sky_light: None,
         }
     }
 
+    // Hora del día entre 0 y 1 dentro del ciclo actual
+    pub fn normalized_time(&self) -> f32 {
+        (self.time_of_day % self.cycle_duration) / self.cycle_duration
+    }
+
     pub fn update_time(&mut self, delta_time: f32) {
         self.time_of_day = (self.time_of_day + delta_time) % 1.0; // Ciclo continuo
         self.update_light();
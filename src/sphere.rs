@@ -1,4 +1,3 @@
-use crate::materials::Material;
 use nalgebra_glm::{Vec3, dot};
 use crate::ray_intersect::{RayIntersect, Intersect};
 use std::f32::consts::PI;
@@ -6,7 +5,7 @@ use std::f32::consts::PI;
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f32,
-    pub material: Material,
+    pub material: usize, // Índice en MaterialLibrary
 }
 
 impl Sphere {
@@ -24,6 +23,19 @@ impl Sphere {
 
         (u, v)
     }
+
+    // Direcciones en las que crecen u (a lo largo del paralelo) y v (hacia el polo superior)
+    fn get_tangent_frame(&self, normal: &Vec3) -> (Vec3, Vec3) {
+        let tangent = Vec3::new(normal.z, 0.0, -normal.x);
+        // En los polos el paralelo degenera; cualquier tangente perpendicular sirve
+        let tangent = if tangent.norm() > 1e-6 {
+            tangent.normalize()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let bitangent = normal.cross(&tangent).normalize();
+        (tangent, bitangent)
+    }
 }
 
 impl RayIntersect for Sphere {
@@ -56,9 +68,35 @@ impl RayIntersect for Sphere {
             let intersection_point = ray_origin + ray_direction * t;
             let normal = (intersection_point - self.center).normalize();
             let (u, v) = self.get_uv(&intersection_point);
-            Intersect::new(intersection_point, normal, t, self.material, u, v)
+            let mut intersect = Intersect::new(intersection_point, normal, t, self.material, u, v);
+            // v recorre medio meridiano (pi * r), el cambio más rápido de las dos coordenadas
+            intersect.uv_density = 1.0 / (PI * self.radius);
+            let (tangent, bitangent) = self.get_tangent_frame(&normal);
+            intersect.tangent = tangent;
+            intersect.bitangent = bitangent;
+            intersect
         } else {
             Intersect::empty()
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tangent_frame_matches_uv_direction() {
+        let sphere = Sphere { center: Vec3::new(0.0, 0.0, 0.0), radius: 1.0, material: 0 };
+
+        let hit = sphere.ray_intersect(&Vec3::new(0.3, 0.2, 5.0), &Vec3::new(0.0, 0.0, -1.0));
+        let step = 1e-2;
+
+        // Avanzar un poco a lo largo de la tangente aumenta u, y a lo largo de la bitangente aumenta v
+        let along_u = (hit.point + hit.tangent * step).normalize();
+        let along_v = (hit.point + hit.bitangent * step).normalize();
+        let (u0, v0) = sphere.get_uv(&hit.point);
+        assert!(sphere.get_uv(&along_u).0 > u0);
+        assert!(sphere.get_uv(&along_v).1 > v0);
+        assert!(hit.tangent.dot(&hit.normal).abs() < 1e-5);
+    }
+}
@@ -1,46 +1,244 @@
 extern crate image;
-use image::{ImageReader, Pixel, DynamicImage, GenericImageView};
+use image::{ImageReader, ImageError, Pixel, DynamicImage, GenericImageView, Rgb, RgbImage};
+use std::error::Error;
 use std::fmt;
+use std::io;
 use crate::color::Color;
 
+// Error al cargar una textura, con la ruta del archivo que lo causó
+#[derive(Debug)]
+pub enum TextureError {
+  Open { path: String, source: io::Error },      // No se pudo abrir o leer el archivo
+  Decode { path: String, source: ImageError },   // El archivo no es una imagen válida
+}
+
+impl fmt::Display for TextureError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TextureError::Open { path, source } => write!(f, "no se pudo abrir la textura {}: {}", path, source),
+      TextureError::Decode { path, source } => write!(f, "no se pudo decodificar la textura {}: {}", path, source),
+    }
+  }
+}
+
+impl Error for TextureError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      TextureError::Open { source, .. } => Some(source),
+      TextureError::Decode { source, .. } => Some(source),
+    }
+  }
+}
+
+// Forma de leer la textura entre texeles y entre niveles de mipmap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+  Nearest,   // Texel más cercano del nivel base, sin mipmaps
+  Bilinear,  // Interpolación entre 4 texeles del nivel de mipmap más cercano
+  Trilinear, // Interpolación bilineal en los dos niveles vecinos, mezclados entre sí
+}
+
+// Qué hacer con las coordenadas que caen fuera de la textura
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+  Repeat, // La textura se repite como un mosaico
+  Clamp,  // Se extiende el texel del borde
+  Mirror, // Se repite reflejándose en cada copia
+}
+
+impl WrapMode {
+  // Lleva un índice de texel arbitrario al rango [0, size)
+  fn wrap(self, index: isize, size: usize) -> usize {
+    let size = size as isize;
+    let wrapped = match self {
+      WrapMode::Repeat => index.rem_euclid(size),
+      WrapMode::Clamp => index.clamp(0, size - 1),
+      WrapMode::Mirror => {
+        let period = index.rem_euclid(2 * size);
+        if period < size { period } else { 2 * size - 1 - period }
+      }
+    };
+    wrapped as usize
+  }
+}
+
+// Un nivel de la cadena de mipmaps
+#[derive(Clone)]
+struct MipLevel {
+  width: usize,
+  height: usize,
+  colors: Vec<[f32; 4]>, // RGBA; el canal alfa va de 0 (transparente) a 255 (opaco)
+}
+
+impl MipLevel {
+  // Reduce el nivel a la mitad promediando bloques de 2x2 texeles
+  fn downsample(&self) -> MipLevel {
+    let width = (self.width / 2).max(1);
+    let height = (self.height / 2).max(1);
+    let mut colors = vec![[0.0; 4]; width * height];
+
+    for y in 0..height {
+      for x in 0..width {
+        let mut sum = [0.0; 4];
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+          let sx = (2 * x + dx).min(self.width - 1);
+          let sy = (2 * y + dy).min(self.height - 1);
+          let texel = self.colors[sy * self.width + sx];
+          for (channel, value) in sum.iter_mut().zip(texel) {
+            *channel += value * 0.25;
+          }
+        }
+        colors[y * width + x] = sum;
+      }
+    }
+
+    MipLevel { width, height, colors }
+  }
+
+  fn texel(&self, x: isize, y: isize, wrap: WrapMode) -> [f32; 4] {
+    let x = wrap.wrap(x, self.width);
+    let y = wrap.wrap(y, self.height);
+    self.colors[y * self.width + x]
+  }
+
+  fn nearest(&self, u: f32, v: f32, wrap: WrapMode) -> [f32; 4] {
+    let x = (u * self.width as f32).floor() as isize;
+    let y = ((1.0 - v) * self.height as f32).floor() as isize;
+    self.texel(x, y, wrap)
+  }
+
+  fn bilinear(&self, u: f32, v: f32, wrap: WrapMode) -> [f32; 4] {
+    // Posición relativa a los centros de los texeles
+    let x = u * self.width as f32 - 0.5;
+    let y = (1.0 - v) * self.height as f32 - 0.5;
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let tx = x - x0;
+    let ty = y - y0;
+    let (x0, y0) = (x0 as isize, y0 as isize);
+
+    let top = lerp(self.texel(x0, y0, wrap), self.texel(x0 + 1, y0, wrap), tx);
+    let bottom = lerp(self.texel(x0, y0 + 1, wrap), self.texel(x0 + 1, y0 + 1, wrap), tx);
+    lerp(top, bottom, ty)
+  }
+}
+
+fn lerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+  [
+    a[0] + (b[0] - a[0]) * t,
+    a[1] + (b[1] - a[1]) * t,
+    a[2] + (b[2] - a[2]) * t,
+    a[3] + (b[3] - a[3]) * t,
+  ]
+}
+
+fn to_color(rgba: [f32; 4]) -> Color {
+  Color::new(rgba[0].round() as i32, rgba[1].round() as i32, rgba[2].round() as i32)
+}
+
 #[derive(Clone)]
 pub struct Texture {
-  image: DynamicImage,
   pub width: usize,
   pub height: usize,
-  color_array: Vec<Color>,
+  mips: Vec<MipLevel>, // mips[0] es la imagen original
 }
 
 impl Texture {
-  pub fn new(file_path: &str) -> Texture {
-    let img = ImageReader::open(file_path).unwrap().decode().unwrap();
-    let width = img.width() as usize;
-    let height = img.height() as usize;
+  pub fn load(file_path: &str) -> Result<Texture, TextureError> {
+    let open_error = |source| TextureError::Open { path: file_path.to_string(), source };
+    // El formato se detecta por el contenido: assets/water.png es en realidad un WebP
+    let img = ImageReader::open(file_path)
+      .map_err(open_error)?
+      .with_guessed_format()
+      .map_err(open_error)?
+      .decode()
+      .map_err(|source| TextureError::Decode { path: file_path.to_string(), source })?;
+    Ok(Texture::from_image(&img))
+  }
+
+  // Tablero magenta y negro que sustituye a las texturas que no se pudieron cargar
+  pub fn checkerboard() -> Texture {
+    let image = RgbImage::from_fn(8, 8, |x, y| {
+      if (x + y) % 2 == 0 { Rgb([255, 0, 255]) } else { Rgb([0, 0, 0]) }
+    });
+    Texture::from_image(&DynamicImage::ImageRgb8(image))
+  }
+
+  pub fn from_image(image: &DynamicImage) -> Texture {
+    let width = image.width() as usize;
+    let height = image.height() as usize;
     let mut texture = Texture {
-      image: img,
       width,
       height,
-      color_array: vec![Color::black(); width * height],
+      mips: Vec::new(),
     };
-    texture.load_color_array();
+    texture.load_color_array(image);
+    texture.build_mip_chain();
     texture
   }
 
-  fn load_color_array(&mut self) {
+  fn load_color_array(&mut self, image: &DynamicImage) {
+    let mut colors = vec![[0.0; 4]; self.width * self.height];
     for x in 0..self.width {
       for y in 0..self.height {
-        let pixel = self.image.get_pixel(x as u32, y as u32).to_rgb();
-        let color = ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | (pixel[2] as u32);
-        self.color_array[y * self.width + x] = Color::from_hex(color);
+        // Conservamos el alfa para los materiales recortados (hojas, rejas...)
+        let pixel = image.get_pixel(x as u32, y as u32).to_rgba();
+        colors[y * self.width + x] = [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32, pixel[3] as f32];
+      }
+    }
+    self.mips = vec![MipLevel { width: self.width, height: self.height, colors }];
+  }
+
+  // Genera los niveles reducidos hasta llegar a 1x1
+  fn build_mip_chain(&mut self) {
+    while let Some(last) = self.mips.last() {
+      if last.width == 1 && last.height == 1 {
+        break;
       }
+      let next = last.downsample();
+      self.mips.push(next);
     }
   }
 
-  pub fn get_color(&self, x: usize, y: usize) -> Color {
-    if x >= self.width || y >= self.height {
-      Color::from_hex(0xFF00FF)
+  pub fn mip_levels(&self) -> usize {
+    self.mips.len()
+  }
+
+  // Nivel de detalle para una huella de `footprint` unidades UV sobre la superficie
+  pub fn level_of_detail(&self, footprint: f32) -> f32 {
+    let texels = footprint * self.width.max(self.height) as f32;
+    if texels > 0.0 {
+      texels.log2().clamp(0.0, (self.mips.len() - 1) as f32)
     } else {
-      self.color_array[y * self.width + x]
+      0.0
+    }
+  }
+
+  // Muestrea la textura en (u, v); `footprint` es el ancho del área cubierta por el rayo en unidades UV
+  pub fn sample(&self, u: f32, v: f32, footprint: f32, filter: FilterMode, wrap: WrapMode) -> Color {
+    to_color(self.sample_rgba(u, v, footprint, filter, wrap))
+  }
+
+  // Opacidad en (u, v), entre 0.0 (transparente) y 1.0 (opaco)
+  pub fn sample_alpha(&self, u: f32, v: f32, footprint: f32, filter: FilterMode, wrap: WrapMode) -> f32 {
+    self.sample_rgba(u, v, footprint, filter, wrap)[3] / 255.0
+  }
+
+  fn sample_rgba(&self, u: f32, v: f32, footprint: f32, filter: FilterMode, wrap: WrapMode) -> [f32; 4] {
+    match filter {
+      FilterMode::Nearest => self.mips[0].nearest(u, v, wrap),
+      FilterMode::Bilinear => {
+        let level = self.level_of_detail(footprint).round() as usize;
+        self.mips[level].bilinear(u, v, wrap)
+      }
+      FilterMode::Trilinear => {
+        let lod = self.level_of_detail(footprint);
+        let level = lod.floor() as usize;
+        let next = (level + 1).min(self.mips.len() - 1);
+        let lower = self.mips[level].bilinear(u, v, wrap);
+        let upper = self.mips[next].bilinear(u, v, wrap);
+        lerp(lower, upper, lod - level as f32)
+      }
     }
   }
 }
@@ -50,6 +248,86 @@ impl fmt::Debug for Texture {
     f.debug_struct("Texture")
       .field("width", &self.width)
       .field("height", &self.height)
+      .field("mip_levels", &self.mip_levels())
       .finish()
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Tablero de ajedrez blanco y negro de 1 texel por casilla
+  fn checker(size: u32) -> Texture {
+    let image = RgbImage::from_fn(size, size, |x, y| {
+      if (x + y) % 2 == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) }
+    });
+    Texture::from_image(&DynamicImage::ImageRgb8(image))
+  }
+
+  #[test]
+  fn test_mip_chain_reaches_one_texel() {
+    let texture = checker(8);
+    assert_eq!(texture.mip_levels(), 4); // 8, 4, 2, 1
+  }
+
+  #[test]
+  fn test_distant_samples_average_the_texture() {
+    let texture = checker(8);
+    // Una huella que cubre toda la textura cae en el último nivel: gris medio
+    let color = texture.sample(0.3, 0.6, 1.0, FilterMode::Trilinear, WrapMode::Repeat);
+    assert!((color.red as i32 - 128).abs() <= 1);
+
+    // Sin mipmaps se obtiene un texel puro
+    let color = texture.sample(0.3, 0.6, 1.0, FilterMode::Nearest, WrapMode::Repeat);
+    assert!(color.red == 0 || color.red == 255);
+  }
+
+  #[test]
+  fn test_bilinear_interpolates_between_texels() {
+    let texture = checker(2);
+    // Justo entre los cuatro texeles del nivel base
+    let color = texture.sample(0.5, 0.5, 0.0, FilterMode::Bilinear, WrapMode::Repeat);
+    assert!((color.red as i32 - 128).abs() <= 1);
+  }
+
+  #[test]
+  fn test_alpha_is_kept() {
+    let image = image::RgbaImage::from_fn(2, 1, |x, _| {
+      if x == 0 { image::Rgba([0, 255, 0, 255]) } else { image::Rgba([0, 255, 0, 0]) }
+    });
+    let texture = Texture::from_image(&DynamicImage::ImageRgba8(image));
+    assert_eq!(texture.sample_alpha(0.25, 0.5, 0.0, FilterMode::Nearest, WrapMode::Clamp), 1.0);
+    assert_eq!(texture.sample_alpha(0.75, 0.5, 0.0, FilterMode::Nearest, WrapMode::Clamp), 0.0);
+  }
+
+  #[test]
+  fn test_load_errors_name_the_file() {
+    match Texture::load("assets/no-existe.png") {
+      Err(error @ TextureError::Open { .. }) => assert!(error.to_string().contains("assets/no-existe.png")),
+      other => panic!("se esperaba un error al abrir: {:?}", other),
+    }
+    // Un archivo que existe pero no es una imagen
+    assert!(matches!(Texture::load("Cargo.toml"), Err(TextureError::Decode { .. })));
+  }
+
+  #[test]
+  fn test_wrap_modes() {
+    assert_eq!(WrapMode::Repeat.wrap(5, 4), 1);
+    assert_eq!(WrapMode::Repeat.wrap(-1, 4), 3);
+    assert_eq!(WrapMode::Clamp.wrap(5, 4), 3);
+    assert_eq!(WrapMode::Clamp.wrap(-1, 4), 0);
+    assert_eq!(WrapMode::Mirror.wrap(4, 4), 3);
+    assert_eq!(WrapMode::Mirror.wrap(-1, 4), 0);
+    assert_eq!(WrapMode::Mirror.wrap(9, 4), 1);
+  }
+
+  #[test]
+  fn test_out_of_range_uv_is_wrapped_not_magenta() {
+    let texture = checker(4);
+    let inside = texture.sample(0.125, 0.875, 0.0, FilterMode::Nearest, WrapMode::Repeat);
+    let repeated = texture.sample(1.125, 1.875, 0.0, FilterMode::Nearest, WrapMode::Repeat);
+    assert_eq!(inside.to_hex(), repeated.to_hex());
+    assert_ne!(repeated.to_hex(), 0xFF00FF);
+  }
+}
@@ -0,0 +1,162 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Lado de los bloques en los que se divide la imagen, en píxeles
+pub const TILE_SIZE: usize = 32;
+
+// Bloque rectangular de la imagen que se renderiza de una vez
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    // Índices (en orden de filas de la imagen) de los píxeles del bloque
+    pub fn pixels(&self, image_width: usize) -> impl Iterator<Item = usize> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| y * image_width + x))
+    }
+}
+
+// Orden en que se renderizan los bloques
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileOrder {
+    Spiral,  // Desde el centro hacia afuera, donde suele estar lo que se mira
+    Hilbert, // Siguiendo la curva de Hilbert: bloques vecinos seguidos, buena localidad de caché
+}
+
+impl TileOrder {
+    pub fn next(self) -> Self {
+        match self {
+            TileOrder::Spiral => TileOrder::Hilbert,
+            TileOrder::Hilbert => TileOrder::Spiral,
+        }
+    }
+}
+
+// Divide la imagen en bloques de `size` píxeles de lado (los del borde pueden ser menores)
+// ordenados según `order`
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+    let mut grid: Vec<(usize, usize)> = (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row))).collect();
+
+    match order {
+        TileOrder::Spiral => {
+            // Primero por anillo alrededor del centro y dentro de cada anillo por ángulo
+            let center_x = (columns as f32 - 1.0) / 2.0;
+            let center_y = (rows as f32 - 1.0) / 2.0;
+            let key = |&(column, row): &(usize, usize)| {
+                let dx = column as f32 - center_x;
+                let dy = row as f32 - center_y;
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+            grid.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        }
+        TileOrder::Hilbert => {
+            let side = columns.max(rows).next_power_of_two();
+            grid.sort_by_key(|&(column, row)| hilbert_index(side, column, row));
+        }
+    }
+
+    grid.into_iter()
+        .map(|(column, row)| Tile {
+            x: column * size,
+            y: row * size,
+            width: size.min(width - column * size),
+            height: size.min(height - row * size),
+        })
+        .collect()
+}
+
+// Posición de (x, y) a lo largo de la curva de Hilbert que recorre una cuadrícula de
+// `side` x `side` celdas (`side` potencia de dos)
+fn hilbert_index(side: usize, mut x: usize, mut y: usize) -> usize {
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = usize::from(x & s > 0);
+        let ry = usize::from(y & s > 0);
+        index += s * s * ((3 * rx) ^ ry);
+        // Rotar el cuadrante para que la curva siga siendo continua
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+// Aviso compartido para abandonar un render a medias (por ejemplo, porque la cámara se movió)
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    // Vuelve a permitir renderizar
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covered_pixels(tiles: &[Tile], width: usize) -> Vec<usize> {
+        let mut pixels: Vec<usize> = tiles.iter().flat_map(|tile| tile.pixels(width)).collect();
+        pixels.sort();
+        pixels
+    }
+
+    #[test]
+    fn test_tiles_cover_the_image_once() {
+        for order in [TileOrder::Spiral, TileOrder::Hilbert] {
+            let tiles = tiles(100, 70, 32, order);
+            assert_eq!(tiles.len(), 4 * 3);
+            assert_eq!(covered_pixels(&tiles, 100), (0..100 * 70).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_spiral_starts_at_the_center() {
+        let tiles = tiles(5 * 16, 5 * 16, 16, TileOrder::Spiral);
+        assert_eq!((tiles[0].x, tiles[0].y), (32, 32));
+        // El primer anillo completo antes que cualquier bloque de la esquina
+        let corner = tiles.iter().position(|tile| tile.x == 0 && tile.y == 0).unwrap();
+        assert!(corner >= 9);
+    }
+
+    #[test]
+    fn test_hilbert_visits_neighbors() {
+        let tiles = tiles(8 * 16, 8 * 16, 16, TileOrder::Hilbert);
+        for pair in tiles.windows(2) {
+            let distance = pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y);
+            assert_eq!(distance, 16);
+        }
+    }
+
+    #[test]
+    fn test_cancel_token_is_shared() {
+        let token = CancelToken::default();
+        let worker = token.clone();
+        token.cancel();
+        assert!(worker.is_cancelled());
+        worker.reset();
+        assert!(!token.is_cancelled());
+    }
+}
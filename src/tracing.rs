@@ -0,0 +1,184 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use nalgebra_glm::Vec3;
+use crate::ambient::SkyLight;
+use crate::clouds::CloudLayer;
+use crate::color::Color;
+use crate::fog::Fog;
+use crate::light::Light;
+use crate::materials::TextureManager;
+use crate::material_library::MaterialLibrary;
+use crate::ray_intersect::{RayIntersect, Intersect, RayCone};
+
+// Color del cielo para los rayos que no golpean nada
+pub const BACKGROUND_COLOR: Color = Color { red: 130, green: 189, blue: 188 };
+
+// Máximo de superficies recortadas que un rayo puede atravesar antes de rendirse
+const MAX_CUTOUT_LAYERS: usize = 16;
+
+// Rayos lanzados desde que empezó el programa (primarios, de sombra, reflejados...), para
+// las estadísticas de rendimiento
+static RAYS_TRACED: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // Rayos de este hilo que todavía no se sumaron a `RAYS_TRACED`: cada rayo solo toca este
+    // contador y el compartido se actualiza una vez por bloque
+    static PENDING_RAYS: Cell<u64> = const { Cell::new(0) };
+}
+
+// Total de rayos ya sumados con `flush_ray_count`
+pub fn rays_traced() -> u64 {
+    RAYS_TRACED.load(Ordering::Relaxed)
+}
+
+// Suma al total los rayos pendientes del hilo actual
+pub fn flush_ray_count() {
+    let pending = PENDING_RAYS.replace(0);
+    if pending > 0 {
+        RAYS_TRACED.fetch_add(pending, Ordering::Relaxed);
+    }
+}
+
+// Suma al total los rayos pendientes de todos los hilos de rayon (y del actual), para los
+// pases que no se renderizan por bloques
+pub fn flush_all_ray_counts() {
+    rayon::broadcast(|_| flush_ray_count());
+    flush_ray_count();
+}
+
+// Datos compartidos por todos los rayos de un cuadro
+pub struct RenderContext<'a> {
+    pub objects: &'a [Box<dyn RayIntersect>],
+    pub lights: &'a [Light],
+    pub texture_manager: &'a TextureManager,
+    pub materials: &'a MaterialLibrary,
+    pub time: f32, // Tiempo de la escena en segundos, para los materiales animados
+    pub fog: Option<Fog>, // Niebla de la escena; la primera luz de `lights` es el sol que la ilumina
+    pub clouds: Option<&'a CloudLayer>,
+    pub sky_light: Option<SkyLight>, // Luz de ambiente del cielo, con oclusión
+}
+
+// Intersección más cercana del rayo con la escena. Los texeles transparentes de los
+// materiales recortados se ignoran y el rayo continúa detrás de ellos.
+pub fn scene_intersect(
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    context: &RenderContext,
+    cone: RayCone,
+) -> Intersect {
+    PENDING_RAYS.set(PENDING_RAYS.get() + 1);
+    let epsilon = 1e-4;
+    let mut origin = *ray_origin;
+    let mut traveled = 0.0;
+
+    for _ in 0..MAX_CUTOUT_LAYERS {
+        let mut intersect = Intersect::empty();
+        let mut zbuffer = f32::INFINITY; // El objeto más cercano golpeado por el rayo
+
+        for (object_id, object) in context.objects.iter().enumerate() {
+            let tmp = object.ray_intersect(&origin, ray_direction);
+            if tmp.is_intersecting && tmp.distance < zbuffer {
+                zbuffer = tmp.distance;
+                intersect = Intersect { object_id, ..tmp };
+            }
+        }
+
+        if !intersect.is_intersecting {
+            return intersect;
+        }
+
+        intersect.distance += traveled;
+        let footprint = cone.uv_footprint(&intersect, ray_direction);
+        let material = context.materials.get(intersect.material_id);
+        if !material.is_cut_out(&intersect, footprint, context.time, context.texture_manager) {
+            return intersect;
+        }
+
+        // Texel transparente: seguimos el rayo justo detrás de la superficie
+        origin = intersect.point + ray_direction * epsilon;
+        traveled = intersect.distance + epsilon;
+    }
+
+    Intersect::empty()
+}
+
+// Radiancia (lineal, 1.0 = 255) del cielo que ve un rayo que no golpea nada
+pub fn sky_radiance(ray_origin: &Vec3, ray_direction: &Vec3, context: &RenderContext) -> Vec3 {
+    let background = BACKGROUND_COLOR.to_vec3();
+    match context.clouds {
+        Some(clouds) => clouds.sky(ray_origin, ray_direction, context.time, context.lights.first(), background),
+        None => background,
+    }
+}
+
+// Fracción de la luz de `light` que llega a `point` a través de las nubes
+pub fn cloud_transmittance(point: &Vec3, light: &Light, context: &RenderContext) -> f32 {
+    match context.clouds {
+        Some(clouds) => {
+            let to_light = light.position - point;
+            clouds.transmittance(point, &to_light.normalize(), to_light.norm(), context.time)
+        }
+        None => 1.0,
+    }
+}
+
+pub fn cast_shadow(
+    intersect: &Intersect,
+    light: &Light,
+    context: &RenderContext,
+    cone: RayCone,
+) -> f32 {
+    let to_light = light.position - intersect.point;
+    let light_dir = to_light.normalize();
+
+    // Ajusta el origen del rayo de sombra para evitar la autointersección
+    let offset = intersect.normal * 1e-4; // Pequeño valor para evitar estar dentro del cubo
+    let shadow_ray_origin = intersect.point + offset;
+
+    // Las hojas recortadas dejan pasar la luz por sus huecos; lo que está detrás de la luz no
+    // le hace sombra
+    let shadow_intersect = scene_intersect(&shadow_ray_origin, &light_dir, context, cone);
+    // Las nubes dan sombras suaves, según cuánta luz dejan pasar
+    if shadow_intersect.is_intersecting && shadow_intersect.distance < to_light.norm() {
+        0.7
+    } else {
+        0.7 * (1.0 - cloud_transmittance(&shadow_ray_origin, light, context))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+
+    fn shadow_with(objects: &[Box<dyn RayIntersect>]) -> f32 {
+        let texture_manager = TextureManager::new();
+        let mut materials = MaterialLibrary::new();
+        materials.add("block", MaterialLibrary::default_material());
+        let light = Light::new(Vec3::new(0.0, 5.0, 0.0), Color::new(255, 255, 255), 1.0);
+        let context = RenderContext {
+            objects,
+            lights: &[],
+            texture_manager: &texture_manager,
+            materials: &materials,
+            time: 0.0,
+            fog: None,
+            clouds: None,
+            sky_light: None,
+        };
+        let mut intersect = Intersect::empty();
+        intersect.point = Vec3::zeros();
+        intersect.normal = Vec3::new(0.0, 1.0, 0.0);
+        cast_shadow(&intersect, &light, &context, RayCone::from_pixel(1.0, 600.0))
+    }
+
+    #[test]
+    fn test_only_objects_before_the_light_cast_shadows() {
+        let between: Vec<Box<dyn RayIntersect>> =
+            vec![Box::new(Cube::new(Vec3::new(-1.0, 2.0, -1.0), Vec3::new(1.0, 3.0, 1.0), 0))];
+        let behind: Vec<Box<dyn RayIntersect>> =
+            vec![Box::new(Cube::new(Vec3::new(-1.0, 6.0, -1.0), Vec3::new(1.0, 7.0, 1.0), 0))];
+        assert_eq!(shadow_with(&between), 0.7);
+        assert_eq!(shadow_with(&behind), 0.0);
+    }
+}
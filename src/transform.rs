@@ -0,0 +1,113 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+// Transformación afín con su inversa precalculada
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub matrix: Mat4,  // Del espacio del objeto al espacio del mundo
+    pub inverse: Mat4, // Del espacio del mundo al espacio del objeto
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            matrix: Mat4::identity(),
+            inverse: Mat4::identity(),
+        }
+    }
+
+    // Construye la transformación a partir de una matriz; None si no es invertible
+    pub fn from_matrix(matrix: Mat4) -> Option<Self> {
+        matrix.try_inverse().map(|inverse| Transform { matrix, inverse })
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Transform {
+            matrix: nalgebra_glm::translation(&offset),
+            inverse: nalgebra_glm::translation(&-offset),
+        }
+    }
+
+    // Rotación de `angle` radianes alrededor de `axis`
+    pub fn rotation(angle: f32, axis: Vec3) -> Self {
+        let matrix = nalgebra_glm::rotation(angle, &axis);
+        Transform {
+            matrix,
+            inverse: matrix.transpose(), // Las rotaciones son ortogonales
+        }
+    }
+
+    // Escala por eje; ningún componente puede ser cero
+    pub fn scaling(factors: Vec3) -> Self {
+        Transform {
+            matrix: nalgebra_glm::scaling(&factors),
+            inverse: nalgebra_glm::scaling(&Vec3::new(1.0 / factors.x, 1.0 / factors.y, 1.0 / factors.z)),
+        }
+    }
+
+    // Aplica primero `self` y después `next`
+    pub fn then(&self, next: &Transform) -> Self {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    // Escala uniforme aproximada (raíz cúbica del cambio de volumen)
+    pub fn scale_factor(&self) -> f32 {
+        self.matrix.fixed_view::<3, 3>(0, 0).determinant().abs().cbrt()
+    }
+
+    pub fn transform_point(&self, point: &Vec3) -> Vec3 {
+        (self.matrix * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+    }
+
+    pub fn transform_vector(&self, vector: &Vec3) -> Vec3 {
+        (self.matrix * Vec4::new(vector.x, vector.y, vector.z, 0.0)).xyz()
+    }
+
+    // Las normales se transforman con la transpuesta de la inversa para seguir siendo perpendiculares
+    pub fn transform_normal(&self, normal: &Vec3) -> Vec3 {
+        (self.inverse.transpose() * Vec4::new(normal.x, normal.y, normal.z, 0.0)).xyz().normalize()
+    }
+
+    pub fn inverse_point(&self, point: &Vec3) -> Vec3 {
+        (self.inverse * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+    }
+
+    pub fn inverse_vector(&self, vector: &Vec3) -> Vec3 {
+        (self.inverse * Vec4::new(vector.x, vector.y, vector.z, 0.0)).xyz()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn approx(a: &Vec3, b: &Vec3) -> bool {
+        (a - b).norm() < 1e-5
+    }
+
+    #[test]
+    fn test_inverse_undoes_transform() {
+        let transform = Transform::scaling(Vec3::new(2.0, 1.0, 0.5))
+            .then(&Transform::rotation(PI / 3.0, Vec3::new(0.0, 1.0, 0.0)))
+            .then(&Transform::translation(Vec3::new(1.0, -2.0, 3.0)));
+
+        let point = Vec3::new(0.3, 0.7, -1.1);
+        assert!(approx(&transform.inverse_point(&transform.transform_point(&point)), &point));
+        assert!(approx(&transform.inverse_vector(&transform.transform_vector(&point)), &point));
+    }
+
+    #[test]
+    fn test_normal_stays_perpendicular_under_scaling() {
+        let transform = Transform::scaling(Vec3::new(4.0, 1.0, 1.0));
+
+        // Plano inclinado 45°: tras escalar en x la normal debe seguir perpendicular a la superficie
+        let tangent = Vec3::new(1.0, 1.0, 0.0);
+        let normal = Vec3::new(1.0, -1.0, 0.0).normalize();
+        let world_tangent = transform.transform_vector(&tangent);
+        let world_normal = transform.transform_normal(&normal);
+        assert!(world_tangent.dot(&world_normal).abs() < 1e-5);
+    }
+}
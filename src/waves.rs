@@ -0,0 +1,75 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Dirección (x, z) y fracción de la longitud de onda de cada componente del oleaje
+const COMPONENTS: [(f32, f32, f32); 3] = [
+    (1.0, 0.0, 1.0),
+    (0.6, 0.8, 0.63),
+    (-0.7, 0.71, 0.41),
+];
+
+// Oleaje procedural: suma de ondas sinusoidales que viajan por el plano XZ
+#[derive(Debug, Clone, Copy)]
+pub struct Waves {
+    pub amplitude: f32,  // Altura de la onda principal, en unidades del mundo
+    pub wavelength: f32, // Distancia entre crestas de la onda principal
+    pub speed: f32,      // Velocidad de avance de las crestas, en unidades por segundo
+}
+
+impl Waves {
+    pub fn new(amplitude: f32, wavelength: f32, speed: f32) -> Self {
+        Waves { amplitude, wavelength, speed }
+    }
+
+    // Pendiente de la superficie (dh/dx, dh/dz) en el punto y el instante dados
+    pub fn slope(&self, point: &Vec3, time: f32) -> (f32, f32) {
+        let mut dh_dx = 0.0;
+        let mut dh_dz = 0.0;
+
+        for (dx, dz, fraction) in COMPONENTS {
+            let wavelength = self.wavelength * fraction;
+            let k = 2.0 * PI / wavelength;
+            let amplitude = self.amplitude * fraction; // Las ondas cortas son también más bajas
+            let phase = k * (dx * point.x + dz * point.z - self.speed * time);
+            let derivative = amplitude * k * phase.cos();
+            dh_dx += derivative * dx;
+            dh_dz += derivative * dz;
+        }
+
+        (dh_dx, dh_dz)
+    }
+
+    // Inclina la normal según la pendiente de las olas; solo se usa la parte del
+    // gradiente tangente a la superficie, así las caras laterales apenas cambian
+    pub fn perturb_normal(&self, normal: &Vec3, point: &Vec3, time: f32) -> Vec3 {
+        let (dh_dx, dh_dz) = self.slope(point, time);
+        let gradient = Vec3::new(dh_dx, 0.0, dh_dz);
+        let tangential = gradient - normal * gradient.dot(normal);
+        (normal - tangential).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waves_move_over_time() {
+        let waves = Waves::new(0.01, 0.5, 0.2);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let point = Vec3::new(0.3, -0.5, 1.2);
+
+        let before = waves.perturb_normal(&up, &point, 0.0);
+        let after = waves.perturb_normal(&up, &point, 0.7);
+        assert!((before - after).norm() > 1e-3);
+        assert!((before.norm() - 1.0).abs() < 1e-5);
+        assert!(before.y > 0.9); // Olas suaves: la normal sigue apuntando hacia arriba
+    }
+
+    #[test]
+    fn test_flat_when_amplitude_is_zero() {
+        let waves = Waves::new(0.0, 0.5, 0.2);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(waves.perturb_normal(&up, &Vec3::new(1.0, 0.0, 2.0), 3.0), up);
+    }
+}
@@ -0,0 +1,293 @@
+use nalgebra_glm::Vec3;
+use crate::materials::{Material, ShadingModel};
+use crate::optics::{reflect, refract, fresnel_dielectric, fresnel_schlick, AIR_IOR};
+use crate::pbr::PbrSurface;
+use crate::ray_intersect::{Intersect, RayCone};
+use crate::sampling::{hammersley, hash_offset};
+use crate::tracing::{RenderContext, scene_intersect, cast_shadow, sky_radiance, BACKGROUND_COLOR};
+use std::f32::consts::PI;
+
+// Profundidad máxima de reflexiones y refracciones
+const MAX_DEPTH: u32 = 3;
+// Rayos por reflejo rugoso en el primer impacto; en los rebotes siguientes basta con uno
+const GLOSSY_SAMPLES: u32 = 8;
+
+// Contribuciones que forman el color de un impacto, separadas para los pases de render.
+// Son radiancia lineal sin recortar; solo el píxel final se lleva a `Color`.
+#[derive(Debug, Clone, Copy)]
+pub struct ShadingComponents {
+    pub emission: Vec3,
+    pub diffuse: Vec3,    // Luz directa difusa y luz del cielo
+    pub specular: Vec3,   // Brillos especulares de las luces
+    pub reflection: Vec3, // Lo que refleja la superficie (espejo, rugoso o dieléctrico)
+    pub refraction: Vec3, // Lo que se ve a través de la superficie
+}
+
+impl ShadingComponents {
+    pub fn total(&self) -> Vec3 {
+        self.emission + self.diffuse + self.specular + self.reflection + self.refraction
+    }
+}
+
+// Trazador de Whitted: luces directas más reflexiones y refracciones recursivas
+pub fn cast_ray(
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    context: &RenderContext,
+    depth: u32,
+    cone: RayCone,
+    medium: Option<&Material>, // Volumen en el que viaja el rayo (None = aire)
+) -> Vec3 {
+
+    if depth > MAX_DEPTH {
+        return BACKGROUND_COLOR.to_vec3(); // Color de fondo si excedemos la profundidad máxima
+    }
+
+    // Verificamos la intersección del rayo con los objetos
+    let intersect = scene_intersect(ray_origin, ray_direction, context, cone);
+
+    if !intersect.is_intersecting {
+        // Cielo con sus nubes, visto a través de la niebla
+        let sky = sky_radiance(ray_origin, ray_direction, context);
+        return match medium {
+            Some(_) => sky,
+            None => apply_fog(sky, ray_origin, ray_direction, f32::INFINITY, context, cone),
+        };
+    }
+
+    let color = shade(ray_origin, ray_direction, &intersect, context, depth, cone, medium).total();
+
+    // Si el rayo viajaba dentro de un volumen transparente, la luz atravesó `distance`
+    // unidades de él y se atenúa (Beer–Lambert): el agua profunda es más oscura y azul
+    // En el aire, la niebla atenúa el color y suma la luz del sol que dispersa
+    match medium {
+        Some(medium) => color.component_mul(&Vec3::from(medium.transmittance(intersect.distance))),
+        None => apply_fog(color, ray_origin, ray_direction, intersect.distance, context, cone),
+    }
+}
+
+// Color del punto `intersect` visto desde el rayo, separado por contribuciones
+pub fn shade(
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    intersect: &Intersect,
+    context: &RenderContext,
+    depth: u32,
+    cone: RayCone,
+    medium: Option<&Material>,
+) -> ShadingComponents {
+    let intersect = *intersect;
+    let material = *context.materials.get(intersect.material_id);
+
+    // Las normales de las primitivas apuntan hacia afuera; si el rayo sale del objeto
+    // (por ejemplo, desde dentro del agua) trabajamos con la normal del lado del rayo
+    let front_face = ray_direction.dot(&intersect.normal) < 0.0;
+    let facing = if front_face { 1.0 } else { -1.0 };
+    let geometric_normal = intersect.normal * facing;
+
+    let view_dir = (ray_origin - intersect.point).normalize();
+    // Área de la textura que cubre este rayo, para elegir el nivel de mipmap
+    let footprint = cone.uv_footprint(&intersect, ray_direction);
+    // Normal de sombreado (con mapas de normales o de alturas); la geométrica se usa para desplazar los rayos
+    let normal = material.shading_normal(&intersect, footprint, context.time, context.texture_manager) * facing;
+    let next_cone = cone.propagate(intersect.distance);
+
+    // Si el material es emisivo, sumamos su emisión
+    let emission = if material.is_emissive() {
+        material.get_emission(&intersect, footprint, context.time, context.texture_manager)
+    } else {
+        Vec3::zeros()
+    };
+
+    let transparency = material.transparency;
+    let epsilon = 1e-3; // Pequeño desplazamiento para evitar "acné"
+
+    // Los dieléctricos reparten la luz entre reflexión y refracción según Fresnel:
+    // casi todo se refracta de frente y casi todo se refleja en ángulos rasantes
+    let cos_i = (-ray_direction.dot(&normal)).max(0.0);
+    let mut refract_color = Vec3::zeros();
+    let mut fresnel = 0.0;
+
+    if transparency > 0.0 {
+        let (eta_i, eta_t) = if front_face {
+            (AIR_IOR, material.refraction_index) // Entrando al objeto
+        } else {
+            (material.refraction_index, AIR_IOR) // Saliendo del objeto
+        };
+        fresnel = fresnel_dielectric(cos_i, eta_i, eta_t);
+
+        if let Some(refract_dir) = refract(ray_direction, &normal, eta_i / eta_t) {
+            let refract_origin = intersect.point - geometric_normal * epsilon;
+            // Al entrar, el rayo refractado viaja dentro de este material; al salir, vuelve al aire
+            let refract_medium = if front_face { Some(&material) } else { None };
+            refract_color = cast_ray(&refract_origin, &refract_dir.normalize(), context, depth + 1, next_cone, refract_medium);
+        }
+    }
+    let refract_weight = transparency * (1.0 - fresnel);
+    let reflect_origin = intersect.point + geometric_normal * epsilon;
+
+    match material.shading {
+        ShadingModel::Phong => {
+            let (diffuse, specular) = phong_lighting(&material, &intersect, &normal, &view_dir, footprint, depth, context, next_cone);
+
+            // Cálculo de reflexión: la reflectividad del material es la de incidencia normal
+            // (Schlick la aumenta en ángulos rasantes), más la parte reflejada por el dieléctrico
+            let reflectivity = material.reflectivity;
+            let mirror = if reflectivity > 0.0 { fresnel_schlick(cos_i, reflectivity) } else { 0.0 };
+            let reflect_weight = mirror + transparency * fresnel;
+            let mut reflect_color = Vec3::zeros();
+
+            if reflect_weight > 0.0 {
+                let reflect_dir = reflect(ray_direction, &normal).normalize();
+                // El rayo reflejado se queda en el mismo medio por el que llegó
+                reflect_color = cast_ray(&reflect_origin, &reflect_dir, context, depth + 1, next_cone, medium);
+            }
+
+            // Combinar resultados: color difuso + especular + reflexión + refracción
+            let surface_weight = (1.0 - mirror - transparency).max(0.0);
+            ShadingComponents {
+                emission: emission * surface_weight,
+                diffuse: diffuse * surface_weight,
+                specular: specular * surface_weight,
+                reflection: reflect_color * reflect_weight,
+                refraction: refract_color * refract_weight,
+            }
+        }
+        ShadingModel::MetallicRoughness => {
+            let surface = material.pbr_surface(&intersect, footprint, context.time, context.texture_manager);
+            let (diffuse, specular) = pbr_lighting(&surface, &intersect, &normal, &view_dir, depth, context, next_cone);
+            let glossy = glossy_reflection(&surface, &intersect, &normal, &view_dir, &reflect_origin, context, depth, next_cone, medium);
+
+            // La parte transparente refleja según Fresnel en lugar de según el BRDF
+            let mut dielectric_reflection = Vec3::zeros();
+            if transparency > 0.0 {
+                let reflect_dir = reflect(ray_direction, &normal).normalize();
+                dielectric_reflection = cast_ray(&reflect_origin, &reflect_dir, context, depth + 1, next_cone, medium);
+            }
+
+            let opacity = 1.0 - transparency;
+            ShadingComponents {
+                emission: emission * opacity,
+                diffuse: diffuse * opacity,
+                specular: specular * opacity,
+                reflection: glossy * opacity + dielectric_reflection * (transparency * fresnel),
+                refraction: refract_color * refract_weight,
+            }
+        }
+    }
+}
+
+// Color visto a través de `distance` unidades de la niebla de la escena
+pub fn apply_fog(color: Vec3, ray_origin: &Vec3, ray_direction: &Vec3, distance: f32, context: &RenderContext, cone: RayCone) -> Vec3 {
+    match &context.fog {
+        Some(fog) => {
+            // Cada píxel empieza a muestrear en un punto distinto: ruido fino en lugar de bandas
+            let (jitter, _) = hash_offset(ray_direction);
+            let (transmittance, inscattered) = fog.march(ray_origin, ray_direction, distance, context, cone, jitter);
+            color * transmittance + inscattered
+        }
+        None => color,
+    }
+}
+
+// Luz de ambiente del cielo que llega a `intersect` (cero si la escena no tiene). En los
+// rebotes basta con una fracción de los rayos de oclusión.
+fn sky_irradiance(intersect: &Intersect, normal: &Vec3, depth: u32, context: &RenderContext, cone: RayCone) -> Vec3 {
+    match &context.sky_light {
+        Some(sky_light) => {
+            let samples = if depth == 0 { sky_light.samples } else { sky_light.samples / 4 };
+            sky_light.irradiance(&intersect.point, normal, samples, context, cone)
+        }
+        None => Vec3::zeros(),
+    }
+}
+
+// Difuso de Lambert y especular de Phong de todas las luces, por separado
+#[allow(clippy::too_many_arguments)]
+fn phong_lighting(material: &Material, intersect: &Intersect, normal: &Vec3, view_dir: &Vec3, footprint: f32, depth: u32, context: &RenderContext, cone: RayCone) -> (Vec3, Vec3) {
+    let diffuse_color = material.get_diffuse_color(intersect, footprint, context.time, context.texture_manager).to_vec3();
+
+    // Luz del cielo: difusa, atenuada en los rincones por la oclusión ambiental
+    let mut diffuse_total = diffuse_color.component_mul(&sky_irradiance(intersect, normal, depth, context, cone)) * material.albedo[0];
+    let mut specular_total = Vec3::zeros();
+
+    // Iteramos sobre todas las luces
+    for light in context.lights {
+        let light_dir = (light.position - intersect.point).normalize();
+        let reflect_dir = reflect(&-light_dir, normal);
+
+        let shadow_intensity = cast_shadow(intersect, light, context, cone);
+        let light_intensity = light.intensity * (1.0 - shadow_intensity);
+
+        // Componente difusa
+        let diffuse_intensity = normal.dot(&light_dir).clamp(0.0, 1.0);
+        let diffuse = diffuse_color * material.albedo[0] * diffuse_intensity * light_intensity;
+
+        // Componente especular
+        let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(material.specular);
+        let specular = light.color.to_vec3() * material.albedo[1] * specular_intensity * light_intensity;
+
+        // Sumar luz difusa y especular de esta luz
+        diffuse_total += diffuse;
+        specular_total += specular;
+    }
+
+    (diffuse_total, specular_total)
+}
+
+// BRDF GGX de todas las luces. La intensidad de la luz se interpreta de forma que una
+// superficie blanca y mate vista de frente devuelva el color de la luz, igual que Phong.
+fn pbr_lighting(surface: &PbrSurface, intersect: &Intersect, normal: &Vec3, view_dir: &Vec3, depth: u32, context: &RenderContext, cone: RayCone) -> (Vec3, Vec3) {
+    // Luz del cielo sobre el lóbulo difuso; la parte especular ya la ven los reflejos
+    let mut diffuse = surface.diffuse_weight(normal, view_dir).component_mul(&sky_irradiance(intersect, normal, depth, context, cone));
+    let mut specular = Vec3::zeros();
+
+    for light in context.lights {
+        let light_dir = (light.position - intersect.point).normalize();
+        let shadow_intensity = cast_shadow(intersect, light, context, cone);
+        let light_radiance = light.color.to_vec3() * (light.intensity * (1.0 - shadow_intensity) * PI);
+        let (diffuse_lobe, specular_lobe) = surface.evaluate_lobes(normal, view_dir, &light_dir);
+        diffuse += diffuse_lobe.component_mul(&light_radiance);
+        specular += specular_lobe.component_mul(&light_radiance);
+    }
+
+    (diffuse, specular)
+}
+
+// Reflejo del entorno ponderado por el lóbulo especular: un rayo si la superficie está
+// pulida, o varias direcciones muestreadas con la GGX si es rugosa
+#[allow(clippy::too_many_arguments)]
+fn glossy_reflection(
+    surface: &PbrSurface,
+    intersect: &Intersect,
+    normal: &Vec3,
+    view_dir: &Vec3,
+    reflect_origin: &Vec3,
+    context: &RenderContext,
+    depth: u32,
+    cone: RayCone,
+    medium: Option<&Material>,
+) -> Vec3 {
+    if surface.is_mirror() {
+        let reflect_dir = reflect(&-view_dir, normal).normalize();
+        let weight = surface.fresnel(normal.dot(view_dir));
+        let color = cast_ray(reflect_origin, &reflect_dir, context, depth + 1, cone, medium);
+        return color.component_mul(&weight);
+    }
+
+    // Las muestras de Hammersley se desplazan distinto en cada punto para cambiar ruido por bandas
+    let samples = if depth == 0 { GLOSSY_SAMPLES } else { 1 };
+    let (offset_u, offset_v) = hash_offset(&intersect.point);
+    let mut sum = Vec3::zeros();
+
+    for index in 0..samples {
+        let (u1, u2) = hammersley(index, samples);
+        let (u1, u2) = ((u1 + offset_u).fract(), (u2 + offset_v).fract());
+        if let Some((reflect_dir, weight)) = surface.sample_specular(normal, view_dir, u1, u2) {
+            let color = cast_ray(reflect_origin, &reflect_dir, context, depth + 1, cone, medium);
+            sum += color.component_mul(&weight);
+        }
+    }
+
+    sum / samples as f32
+}